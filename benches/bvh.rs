@@ -0,0 +1,71 @@
+//! Compares closest-hit traversal of the pointer-based `Bvh` against the flattened `LinearBvh`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use ray_tracing_in_one_weekend::geom::{Bvh, Hittable, LinearBvh, Sphere};
+use ray_tracing_in_one_weekend::mat::Lambertian;
+use ray_tracing_in_one_weekend::ray::Ray;
+use ray_tracing_in_one_weekend::vec3::Point3;
+
+const RAYS_PER_ITER: usize = 1_000;
+
+fn random_spheres(count: usize) -> Vec<Box<dyn Hittable>> {
+    let mut rng = StdRng::seed_from_u64(0);
+    (0..count)
+        .map(|_| {
+            let center = Point3::new(
+                rng.gen_range(-100.0, 100.0),
+                rng.gen_range(-100.0, 100.0),
+                rng.gen_range(-100.0, 100.0),
+            );
+            let radius = rng.gen_range(0.2, 1.5);
+            Box::new(Sphere::new(center, radius, Lambertian::default())) as Box<dyn Hittable>
+        })
+        .collect()
+}
+
+fn random_rays() -> Vec<Ray> {
+    let mut rng = StdRng::seed_from_u64(1);
+    (0..RAYS_PER_ITER)
+        .map(|_| {
+            let origin = Point3::new(rng.gen_range(-10.0, 10.0), 0.0, 150.0);
+            let target = Point3::new(
+                rng.gen_range(-100.0, 100.0),
+                rng.gen_range(-100.0, 100.0),
+                rng.gen_range(-100.0, 100.0),
+            );
+            Ray::new(origin, target - origin)
+        })
+        .collect()
+}
+
+fn trace_all(world: &dyn Hittable, rays: &[Ray]) -> usize {
+    rays.iter()
+        .filter(|ray| world.hit(ray, (0.001, f64::MAX)).is_some())
+        .count()
+}
+
+fn traversal(c: &mut Criterion) {
+    let rays = random_rays();
+    let mut group = c.benchmark_group("bvh_traversal");
+
+    for &count in &[1_000, 10_000, 100_000] {
+        let tree = Bvh::new(random_spheres(count), 0.0, 0.0).unwrap();
+        let linear = LinearBvh::new(random_spheres(count), 0.0, 0.0).unwrap();
+        assert_eq!(trace_all(&tree, &rays), trace_all(&linear, &rays));
+
+        group.bench_with_input(BenchmarkId::new("tree", count), &tree, |b, tree| {
+            b.iter(|| trace_all(tree, &rays))
+        });
+        group.bench_with_input(BenchmarkId::new("linear", count), &linear, |b, linear| {
+            b.iter(|| trace_all(linear, &rays))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, traversal);
+criterion_main!(benches);
@@ -1,5 +1,5 @@
 use crate::ray::Ray;
-use crate::vec3::Point3;
+use crate::vec3::{Point3, Vec3};
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Aabb {
@@ -13,11 +13,53 @@ impl Aabb {
     }
 
     #[inline]
-    pub fn hit(&self, ray: &Ray, (mut t_min, mut t_max): (f64, f64)) -> bool {
+    pub fn centroid(&self) -> Point3 {
+        (self.min + self.max) / 2.0
+    }
+
+    #[inline]
+    pub fn surface_area(&self) -> f64 {
+        let extent = self.max - self.min;
+        2.0 * (extent.x * extent.y + extent.y * extent.z + extent.z * extent.x)
+    }
+
+    /// Returns a copy of this box with any axis thinner than `delta` widened to `delta`.
+    ///
+    /// Planar primitives produce boxes with zero thickness along one axis, which `Aabb::hit` would
+    /// otherwise always reject.
+    pub fn padded(self, delta: f64) -> Self {
+        let mut padded = self;
+        for a in 0..3 {
+            if padded.max[a] - padded.min[a] < delta {
+                padded.min[a] -= delta / 2.0;
+                padded.max[a] += delta / 2.0;
+            }
+        }
+        padded
+    }
+
+    #[inline]
+    pub fn hit(&self, ray: &Ray, t_range: (f64, f64)) -> bool {
+        let inv_direction = Vec3::new(
+            1.0 / ray.direction.x,
+            1.0 / ray.direction.y,
+            1.0 / ray.direction.z,
+        );
+        self.hit_precomputed(ray.origin, inv_direction, t_range)
+    }
+
+    /// Like `hit()`, but reuses the reciprocal of the ray direction across many boxes.
+    #[inline]
+    pub fn hit_precomputed(
+        &self,
+        origin: Point3,
+        inv_direction: Vec3,
+        (mut t_min, mut t_max): (f64, f64),
+    ) -> bool {
         for a in 0..3 {
-            let inv_d = 1.0 / ray.direction[a];
-            let mut time0 = (self.min[a] - ray.origin[a]) * inv_d;
-            let mut time1 = (self.max[a] - ray.origin[a]) * inv_d;
+            let inv_d = inv_direction[a];
+            let mut time0 = (self.min[a] - origin[a]) * inv_d;
+            let mut time1 = (self.max[a] - origin[a]) * inv_d;
 
             if inv_d < 0.0 {
                 std::mem::swap(&mut time0, &mut time1);
@@ -1,91 +1,374 @@
+pub use self::panoramic::{EquirectangularCamera, FisheyeCamera};
+pub use self::physical::PhysicalCamera;
+pub use self::planar::{OrthographicCamera, PerspectiveCamera};
+
+use std::fmt::Debug;
+use std::str::FromStr;
 use std::time::Duration;
 
-use rand::Rng;
+use anyhow::format_err;
+use rand::{Rng, RngCore};
 
 use crate::ray::Ray;
 use crate::vec3::{Point3, Vec3};
 
-#[derive(Clone, Debug, PartialEq)]
-pub struct Camera {
+mod panoramic;
+mod physical;
+mod planar;
+
+/// Maps points on the image to the rays that are traced through them.
+pub trait Camera: Debug + Send + Sync {
+    /// Returns a ray through the given screen coordinates, where `(0, 0)` is the lower left
+    /// corner of the image and `(1, 1)` the upper right, sampling the lens and shutter with `rng`.
+    fn ray_at(&self, screen_x: f64, screen_y: f64, rng: &mut dyn RngCore) -> Ray;
+}
+
+impl<C: Camera + ?Sized> Camera for Box<C> {
+    fn ray_at(&self, screen_x: f64, screen_y: f64, rng: &mut dyn RngCore) -> Ray {
+        (**self).ray_at(screen_x, screen_y, rng)
+    }
+}
+
+/// The projection a `CameraSettings` builds its camera with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Projection {
+    /// A thin-lens pinhole camera, see `PerspectiveCamera`.
+    #[default]
+    Perspective,
+    /// Parallel rays, see `OrthographicCamera`.
+    Orthographic,
+    /// An equidistant fisheye lens, see `FisheyeCamera`.
+    Fisheye,
+    /// A full 360° latitude-longitude panorama, see `EquirectangularCamera`.
+    Equirectangular,
+}
+
+impl FromStr for Projection {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "perspective" => Ok(Projection::Perspective),
+            "orthographic" => Ok(Projection::Orthographic),
+            "fisheye" => Ok(Projection::Fisheye),
+            "equirectangular" => Ok(Projection::Equirectangular),
+            _ => Err(format_err!(
+                "Unknown projection `{}`, expected one of: perspective, orthographic, fisheye, \
+                 equirectangular",
+                s
+            )),
+        }
+    }
+}
+
+/// An orthonormal camera frame, with `w` pointing backwards away from the viewing direction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frame {
     pub origin: Point3,
-    pub lower_left_corner: Point3,
-    pub horizontal: Vec3,
-    pub vertical: Vec3,
     pub u: Vec3,
     pub v: Vec3,
     pub w: Vec3,
-    pub lens_radius: f64,
-    pub shutter_duration: Duration,
 }
 
-impl Camera {
-    pub fn new(
-        up_vec: Vec3,
-        look_from: Point3,
-        look_at: Point3,
-        vertical_fov_deg: f64,
-        aspect_ratio: f64,
-        aperture: f64,
-        focus_dist: f64,
-        shutter_duration: Duration,
-    ) -> Self {
-        let theta = vertical_fov_deg.to_radians();
-        let h = (theta / 2.0).tan();
-        let viewport_height = 2.0 * h;
-        let viewport_width = aspect_ratio * viewport_height;
-
+impl Frame {
+    pub fn new(up_vec: Vec3, look_from: Point3, look_at: Point3) -> Self {
         let w = (look_from - look_at).to_unit();
         let u = up_vec.cross(w).to_unit();
         let v = w.cross(u);
 
-        let origin = look_from;
-        let horizontal = focus_dist * viewport_width * u;
-        let vertical = focus_dist * viewport_height * v;
-
-        Camera {
-            origin,
-            horizontal,
-            vertical,
+        Frame {
+            origin: look_from,
             u,
             v,
             w,
-            lower_left_corner: origin - horizontal / 2.0 - vertical / 2.0 - focus_dist * w,
-            lens_radius: aperture / 2.0,
-            shutter_duration,
         }
     }
 
-    pub fn ray_at(&self, screen_x: f64, screen_y: f64) -> Ray {
-        let (s, t) = (screen_x, screen_y);
-        let rd = self.lens_radius * Vec3::random_in_unit_disk();
-        let offset = self.u * rd.x + self.v * rd.y;
-        let emission_time = {
-            let shutter_open = 0.0;
-            let shutter_closed = self.shutter_duration.as_secs_f64();
-            rand::thread_rng().gen_range(shutter_open, shutter_closed)
-        };
+    /// Converts a direction from camera space, where the camera looks down `-z`, to world space.
+    #[inline]
+    pub fn to_world(&self, direction: Vec3) -> Vec3 {
+        direction.x * self.u + direction.y * self.v + direction.z * self.w
+    }
+}
 
-        Ray::with_time(
-            self.origin + offset,
-            self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin - offset,
-            emission_time,
-        )
+/// Picks the time a ray is emitted at uniformly while the shutter is open.
+fn sample_time(shutter_duration: Duration, rng: &mut dyn RngCore) -> f64 {
+    let shutter_open = 0.0;
+    let shutter_closed = shutter_duration.as_secs_f64();
+    if shutter_closed > shutter_open {
+        rng.gen_range(shutter_open, shutter_closed)
+    } else {
+        shutter_open
     }
 }
 
-impl Default for Camera {
-    fn default() -> Self {
-        let look_from = Point3::new(3.0, 3.0, 2.0);
-        let look_at = Point3::new(0.0, 0.0, -1.0);
-        Camera::new(
-            Vec3::new(0.0, 1.0, 0.0),
+/// A description of a camera independent of the image's aspect ratio.
+///
+/// Scenes provide these as their default viewpoint, which may then be adjusted, e.g. from the
+/// command line, before building the final `Camera`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CameraSettings {
+    pub projection: Projection,
+    pub look_from: Point3,
+    pub look_at: Point3,
+    pub up_vec: Vec3,
+    /// Vertical field of view. Orthographic cameras instead cover the height a perspective camera
+    /// would see at the focus distance, and equirectangular cameras always see everything.
+    pub vertical_fov_deg: f64,
+    /// Lens diameter, which only perspective cameras use.
+    pub aperture: f64,
+    /// Distance to the plane in perfect focus, defaulting to the distance to `look_at`.
+    pub focus_dist: Option<f64>,
+    pub shutter_duration: Duration,
+    /// Physical camera settings, which replace the field of view, aperture and shutter duration
+    /// above, and determine the exposure.
+    pub physical: Option<PhysicalCamera>,
+}
+
+impl CameraSettings {
+    pub fn new(look_from: Point3, look_at: Point3, vertical_fov_deg: f64) -> Self {
+        CameraSettings {
             look_from,
             look_at,
-            20.0,
-            16.0 / 9.0,
+            vertical_fov_deg,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_projection(mut self, val: Projection) -> Self {
+        self.projection = val;
+        self
+    }
+
+    pub fn with_up_vec(mut self, val: Vec3) -> Self {
+        self.up_vec = val;
+        self
+    }
+
+    pub fn with_aperture(mut self, val: f64) -> Self {
+        self.aperture = val;
+        self
+    }
+
+    pub fn with_focus_dist(mut self, val: f64) -> Self {
+        self.focus_dist = Some(val);
+        self
+    }
+
+    pub fn with_shutter_duration(mut self, val: Duration) -> Self {
+        self.shutter_duration = val;
+        self
+    }
+
+    pub fn with_physical(mut self, val: PhysicalCamera) -> Self {
+        self.physical = Some(val);
+        self
+    }
+
+    /// Returns the factor the rendered radiance should be scaled by, which is only ever not `1.0`
+    /// for physical cameras.
+    pub fn exposure_scale(&self) -> f64 {
+        self.physical
+            .as_ref()
+            .map_or(1.0, PhysicalCamera::exposure_scale)
+    }
+
+    pub fn build(&self, aspect_ratio: f64) -> Box<dyn Camera> {
+        let focus_dist = self
+            .focus_dist
+            .unwrap_or_else(|| (self.look_from - self.look_at).len());
+        let (vertical_fov_deg, aperture, shutter_duration) = match &self.physical {
+            Some(physical) => (
+                physical.vertical_fov_deg(aspect_ratio),
+                physical.aperture(),
+                physical.shutter,
+            ),
+            None => (self.vertical_fov_deg, self.aperture, self.shutter_duration),
+        };
+
+        match self.projection {
+            Projection::Perspective => Box::new(PerspectiveCamera::new(
+                self.up_vec,
+                self.look_from,
+                self.look_at,
+                vertical_fov_deg,
+                aspect_ratio,
+                aperture,
+                focus_dist,
+                shutter_duration,
+            )),
+            Projection::Orthographic => {
+                let viewport_height =
+                    2.0 * focus_dist * (vertical_fov_deg.to_radians() / 2.0).tan();
+                Box::new(OrthographicCamera::new(
+                    self.up_vec,
+                    self.look_from,
+                    self.look_at,
+                    viewport_height,
+                    aspect_ratio,
+                    shutter_duration,
+                ))
+            }
+            Projection::Fisheye => Box::new(FisheyeCamera::new(
+                self.up_vec,
+                self.look_from,
+                self.look_at,
+                vertical_fov_deg,
+                aspect_ratio,
+                shutter_duration,
+            )),
+            Projection::Equirectangular => Box::new(EquirectangularCamera::new(
+                self.up_vec,
+                self.look_from,
+                self.look_at,
+                shutter_duration,
+            )),
+        }
+    }
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        CameraSettings {
+            projection: Projection::Perspective,
+            look_from: Point3::new(3.0, 3.0, 2.0),
+            look_at: Point3::new(0.0, 0.0, -1.0),
+            up_vec: Vec3::new(0.0, 1.0, 0.0),
+            vertical_fov_deg: 20.0,
+            aperture: 0.0,
+            focus_dist: None,
+            shutter_duration: Duration::from_secs(0),
+            physical: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use super::*;
+    use crate::random;
+
+    fn settings(projection: Projection) -> CameraSettings {
+        CameraSettings::new(Point3::new(0.0, 0.0, 5.0), Point3::zeros(), 90.0)
+            .with_projection(projection)
+    }
+
+    fn direction(camera: &dyn Camera, x: f64, y: f64) -> Vec3 {
+        camera
+            .ray_at(x, y, &mut random::stream_rng(0, 0))
+            .direction
+            .to_unit()
+    }
+
+    #[test]
+    fn every_projection_looks_at_the_target_from_the_center() {
+        let projections = [
+            Projection::Perspective,
+            Projection::Orthographic,
+            Projection::Fisheye,
+            Projection::Equirectangular,
+        ];
+        for &projection in &projections {
+            let camera = settings(projection).build(2.0);
+            let ray = camera.ray_at(0.5, 0.5, &mut random::stream_rng(0, 0));
+            assert_eq!(ray.origin, Point3::new(0.0, 0.0, 5.0), "{:?}", projection);
+            let forward = ray.direction.to_unit();
+            assert_float_eq!(forward.z, -1.0, abs <= 1e-12, "{:?}", projection);
+        }
+    }
+
+    #[test]
+    fn orthographic_rays_are_parallel() {
+        let camera = settings(Projection::Orthographic).build(2.0);
+        let mut rng = random::stream_rng(0, 0);
+        let corner = camera.ray_at(0.0, 0.0, &mut rng);
+        let center = camera.ray_at(0.5, 0.5, &mut rng);
+        assert_eq!(corner.direction.to_unit(), center.direction.to_unit());
+        // A 90° field of view at distance 5 spans 10 units vertically.
+        assert_float_eq!(corner.origin.x, -10.0, abs <= 1e-9);
+        assert_float_eq!(corner.origin.y, -5.0, abs <= 1e-9);
+    }
+
+    #[test]
+    fn fisheye_angle_grows_linearly_with_radius() {
+        let camera = settings(Projection::Fisheye)
+            .with_up_vec(Vec3::new(0.0, 1.0, 0.0))
+            .build(1.0);
+        // Halfway to the top edge of a 90° fisheye is 22.5° off axis.
+        let up = direction(&*camera, 0.5, 0.75);
+        assert_float_eq!(up.y.atan2(-up.z).to_degrees(), 22.5, abs <= 1e-9);
+        let top = direction(&*camera, 0.5, 1.0);
+        assert_float_eq!(top.y.atan2(-top.z).to_degrees(), 45.0, abs <= 1e-9);
+    }
+
+    #[test]
+    fn equirectangular_covers_the_sphere() {
+        let camera = settings(Projection::Equirectangular).build(2.0);
+        assert_float_eq!(direction(&*camera, 0.5, 1.0).y, 1.0, abs <= 1e-12);
+        assert_float_eq!(direction(&*camera, 0.5, 0.0).y, -1.0, abs <= 1e-12);
+        assert_float_eq!(direction(&*camera, 0.75, 0.5).x, 1.0, abs <= 1e-12);
+        assert_float_eq!(direction(&*camera, 0.0, 0.5).z, 1.0, abs <= 1e-12);
+    }
+
+    #[test]
+    fn physical_camera_derives_lens_and_exposure() {
+        let physical = PhysicalCamera::new(50.0, 2.0, 100.0, Duration::from_secs_f64(0.25))
+            .with_meters_per_unit(0.01);
+        // A 36 by 24 mm sensor behind a 50 mm lens, cropped to 18 mm high for a 2:1 image.
+        assert_float_eq!(
+            physical.vertical_fov_deg(2.0),
+            2.0 * 9f64.atan2(50.0).to_degrees(),
+            abs <= 1e-12
+        );
+        assert_float_eq!(
+            physical.vertical_fov_deg(1.0),
+            2.0 * 12f64.atan2(50.0).to_degrees(),
+            abs <= 1e-12
+        );
+        // A 25 mm aperture in centimetre world units.
+        assert_float_eq!(physical.aperture(), 2.5, abs <= 1e-12);
+        assert_float_eq!(physical.ev100(), 4.0, abs <= 1e-12);
+
+        // Each doubling of ISO or shutter time, or halving of the f-number squared, adds a stop.
+        let brighter = PhysicalCamera {
+            iso: 200.0,
+            ..physical.clone()
+        };
+        assert_float_eq!(
+            brighter.exposure_scale() / physical.exposure_scale(),
             2.0,
-            (look_from - look_at).len(),
-            Duration::from_secs(0),
-        )
+            rel <= 1e-12
+        );
+
+        let settings = settings(Projection::Perspective).with_physical(physical.clone());
+        assert_eq!(settings.exposure_scale(), physical.exposure_scale());
+        assert_eq!(CameraSettings::default().exposure_scale(), 1.0);
+    }
+
+    #[test]
+    fn physical_camera_rejects_settings_without_an_exposure() {
+        assert!(PhysicalCamera::default().validate().is_ok());
+        let invalid = [
+            PhysicalCamera::new(50.0, 2.8, 100.0, Duration::from_secs(0)),
+            PhysicalCamera::new(50.0, 0.0, 100.0, Duration::from_secs(1)),
+            PhysicalCamera::new(50.0, 2.8, -100.0, Duration::from_secs(1)),
+            PhysicalCamera::new(f64::NAN, 2.8, 100.0, Duration::from_secs(1)),
+            PhysicalCamera::default().with_sensor_size(36.0, f64::INFINITY),
+            PhysicalCamera::default().with_meters_per_unit(0.0),
+        ];
+        for physical in &invalid {
+            assert!(physical.validate().is_err(), "{:?} was accepted", physical);
+        }
+    }
+
+    #[test]
+    fn parses_projection_names() {
+        assert_eq!(
+            "fisheye".parse::<Projection>().unwrap(),
+            Projection::Fisheye
+        );
+        assert!("cylindrical".parse::<Projection>().is_err());
     }
 }
@@ -0,0 +1,106 @@
+use std::f64::consts::PI;
+use std::time::Duration;
+
+use rand::RngCore;
+
+use super::{sample_time, Camera, Frame};
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+
+/// An equidistant fisheye camera, where the angle between a ray and the viewing direction grows
+/// linearly with its distance from the image center.
+///
+/// The field of view spans the image's height; wider images see correspondingly more to the
+/// sides. Fields of view up to 360° are supported.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FisheyeCamera {
+    pub frame: Frame,
+    pub aspect_ratio: f64,
+    /// Angle in radians between the viewing direction and the top and bottom edges of the image.
+    pub half_fov: f64,
+    pub shutter_duration: Duration,
+}
+
+impl FisheyeCamera {
+    pub fn new(
+        up_vec: Vec3,
+        look_from: Point3,
+        look_at: Point3,
+        vertical_fov_deg: f64,
+        aspect_ratio: f64,
+        shutter_duration: Duration,
+    ) -> Self {
+        FisheyeCamera {
+            frame: Frame::new(up_vec, look_from, look_at),
+            aspect_ratio,
+            half_fov: vertical_fov_deg.to_radians() / 2.0,
+            shutter_duration,
+        }
+    }
+}
+
+impl Camera for FisheyeCamera {
+    fn ray_at(&self, screen_x: f64, screen_y: f64, rng: &mut dyn RngCore) -> Ray {
+        // Image coordinates scaled so that the top and bottom edges are at a radius of one.
+        let x = (2.0 * screen_x - 1.0) * self.aspect_ratio;
+        let y = 2.0 * screen_y - 1.0;
+        let radius = x.hypot(y);
+
+        let direction = if radius > 0.0 {
+            let theta = radius * self.half_fov;
+            let sin_theta = theta.sin();
+            Vec3::new(sin_theta * x / radius, sin_theta * y / radius, -theta.cos())
+        } else {
+            Vec3::new(0.0, 0.0, -1.0)
+        };
+
+        Ray::with_time(
+            self.frame.origin,
+            self.frame.to_world(direction),
+            sample_time(self.shutter_duration, rng),
+        )
+    }
+}
+
+/// A camera seeing in every direction, with longitude along the image's width and latitude along
+/// its height, as used for environment maps.
+///
+/// The viewing direction is at the center of the image, and the image should be twice as wide as
+/// it is high for pixels to cover equal angles in both directions.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EquirectangularCamera {
+    pub frame: Frame,
+    pub shutter_duration: Duration,
+}
+
+impl EquirectangularCamera {
+    pub fn new(
+        up_vec: Vec3,
+        look_from: Point3,
+        look_at: Point3,
+        shutter_duration: Duration,
+    ) -> Self {
+        EquirectangularCamera {
+            frame: Frame::new(up_vec, look_from, look_at),
+            shutter_duration,
+        }
+    }
+}
+
+impl Camera for EquirectangularCamera {
+    fn ray_at(&self, screen_x: f64, screen_y: f64, rng: &mut dyn RngCore) -> Ray {
+        let longitude = (2.0 * screen_x - 1.0) * PI;
+        let latitude = (screen_y - 0.5) * PI;
+        let direction = Vec3::new(
+            latitude.cos() * longitude.sin(),
+            latitude.sin(),
+            -latitude.cos() * longitude.cos(),
+        );
+
+        Ray::with_time(
+            self.frame.origin,
+            self.frame.to_world(direction),
+            sample_time(self.shutter_duration, rng),
+        )
+    }
+}
@@ -0,0 +1,110 @@
+use std::time::Duration;
+
+use anyhow::format_err;
+
+/// A camera described by the settings of a real one, from which the field of view, the lens
+/// aperture and the exposure follow.
+///
+/// Exposure uses the saturation-based sensitivity model, so radiance in the scene is interpreted
+/// as luminance in cd/m². A scene lit as brightly as a sunny day, with skies in the thousands of
+/// cd/m², then looks right with "sunny 16" settings (f/16, 1/100 s at ISO 100), while the unit
+/// radiance of the books' scenes calls for exposure values around zero, such as f/1.4 and 1/2 s.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PhysicalCamera {
+    pub focal_length_mm: f64,
+    /// Width and height of the sensor; the image is the largest centered crop of it with the
+    /// image's aspect ratio.
+    pub sensor_size_mm: (f64, f64),
+    /// The f-number `N`, i.e. the focal length divided by the aperture diameter.
+    pub f_stop: f64,
+    pub iso: f64,
+    /// How long the shutter stays open, which also determines the extent of motion blur.
+    pub shutter: Duration,
+    /// The length of one world unit, used to size the aperture in world units.
+    pub meters_per_unit: f64,
+}
+
+impl PhysicalCamera {
+    pub fn new(focal_length_mm: f64, f_stop: f64, iso: f64, shutter: Duration) -> Self {
+        PhysicalCamera {
+            focal_length_mm,
+            f_stop,
+            iso,
+            shutter,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_sensor_size(mut self, width_mm: f64, height_mm: f64) -> Self {
+        self.sensor_size_mm = (width_mm, height_mm);
+        self
+    }
+
+    pub fn with_meters_per_unit(mut self, val: f64) -> Self {
+        self.meters_per_unit = val;
+        self
+    }
+
+    /// Checks that every setting is positive and finite, since the field of view, aperture and
+    /// exposure are meaningless otherwise.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let (sensor_width, sensor_height) = self.sensor_size_mm;
+        let settings = [
+            ("focal length", self.focal_length_mm),
+            ("sensor width", sensor_width),
+            ("sensor height", sensor_height),
+            ("f-stop", self.f_stop),
+            ("ISO", self.iso),
+            ("shutter time", self.shutter.as_secs_f64()),
+            ("meters per unit", self.meters_per_unit),
+        ];
+        match settings
+            .iter()
+            .find(|(_, value)| !(value.is_finite() && *value > 0.0))
+        {
+            Some((name, value)) => Err(format_err!(
+                "The physical camera's {} must be positive, found {}",
+                name,
+                value
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the vertical field of view of an image with the given aspect ratio.
+    pub fn vertical_fov_deg(&self, aspect_ratio: f64) -> f64 {
+        let (sensor_width, sensor_height) = self.sensor_size_mm;
+        let image_height = sensor_height.min(sensor_width / aspect_ratio);
+        (2.0 * (image_height / (2.0 * self.focal_length_mm)).atan()).to_degrees()
+    }
+
+    /// Returns the diameter of the aperture in world units.
+    pub fn aperture(&self) -> f64 {
+        self.focal_length_mm / self.f_stop / 1000.0 / self.meters_per_unit
+    }
+
+    /// Returns the exposure value at ISO 100 that is equivalent to these settings.
+    pub fn ev100(&self) -> f64 {
+        (self.f_stop.powi(2) / self.shutter.as_secs_f64() * 100.0 / self.iso).log2()
+    }
+
+    /// Returns the factor that converts scene luminance to film values, where `1.0` is the
+    /// brightest value the sensor records before saturating.
+    pub fn exposure_scale(&self) -> f64 {
+        1.0 / (1.2 * 2f64.powf(self.ev100()))
+    }
+}
+
+impl Default for PhysicalCamera {
+    /// A 50 mm lens on a full-frame sensor at f/2.8, 1/60 s and ISO 100.
+    fn default() -> Self {
+        PhysicalCamera {
+            focal_length_mm: 50.0,
+            sensor_size_mm: (36.0, 24.0),
+            f_stop: 2.8,
+            iso: 100.0,
+            shutter: Duration::from_secs_f64(1.0 / 60.0),
+            meters_per_unit: 1.0,
+        }
+    }
+}
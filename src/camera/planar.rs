@@ -0,0 +1,133 @@
+use std::time::Duration;
+
+use rand::RngCore;
+
+use super::{sample_time, Camera, CameraSettings, Frame};
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+
+/// A thin-lens camera projecting the scene onto a plane, with depth of field for nonzero apertures.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PerspectiveCamera {
+    pub origin: Point3,
+    pub lower_left_corner: Point3,
+    pub horizontal: Vec3,
+    pub vertical: Vec3,
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+    pub lens_radius: f64,
+    pub shutter_duration: Duration,
+}
+
+impl PerspectiveCamera {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        up_vec: Vec3,
+        look_from: Point3,
+        look_at: Point3,
+        vertical_fov_deg: f64,
+        aspect_ratio: f64,
+        aperture: f64,
+        focus_dist: f64,
+        shutter_duration: Duration,
+    ) -> Self {
+        let theta = vertical_fov_deg.to_radians();
+        let h = (theta / 2.0).tan();
+        let viewport_height = 2.0 * h;
+        let viewport_width = aspect_ratio * viewport_height;
+
+        let Frame { origin, u, v, w } = Frame::new(up_vec, look_from, look_at);
+        let horizontal = focus_dist * viewport_width * u;
+        let vertical = focus_dist * viewport_height * v;
+
+        PerspectiveCamera {
+            origin,
+            horizontal,
+            vertical,
+            u,
+            v,
+            w,
+            lower_left_corner: origin - horizontal / 2.0 - vertical / 2.0 - focus_dist * w,
+            lens_radius: aperture / 2.0,
+            shutter_duration,
+        }
+    }
+}
+
+impl Camera for PerspectiveCamera {
+    fn ray_at(&self, screen_x: f64, screen_y: f64, rng: &mut dyn RngCore) -> Ray {
+        let (s, t) = (screen_x, screen_y);
+        let rd = self.lens_radius * Vec3::random_in_unit_disk(rng);
+        let offset = self.u * rd.x + self.v * rd.y;
+
+        Ray::with_time(
+            self.origin + offset,
+            self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin - offset,
+            sample_time(self.shutter_duration, rng),
+        )
+    }
+}
+
+impl Default for PerspectiveCamera {
+    fn default() -> Self {
+        let settings = CameraSettings::default();
+        PerspectiveCamera::new(
+            settings.up_vec,
+            settings.look_from,
+            settings.look_at,
+            settings.vertical_fov_deg,
+            16.0 / 9.0,
+            2.0,
+            (settings.look_from - settings.look_at).len(),
+            settings.shutter_duration,
+        )
+    }
+}
+
+/// A camera whose rays all travel in the viewing direction, starting from a rectangle of
+/// `viewport_height` world units centered on `look_from`.
+///
+/// Parallel lines stay parallel and sizes do not shrink with distance, which suits technical
+/// views of a scene.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OrthographicCamera {
+    pub lower_left_corner: Point3,
+    pub horizontal: Vec3,
+    pub vertical: Vec3,
+    pub direction: Vec3,
+    pub shutter_duration: Duration,
+}
+
+impl OrthographicCamera {
+    pub fn new(
+        up_vec: Vec3,
+        look_from: Point3,
+        look_at: Point3,
+        viewport_height: f64,
+        aspect_ratio: f64,
+        shutter_duration: Duration,
+    ) -> Self {
+        let Frame { origin, u, v, w } = Frame::new(up_vec, look_from, look_at);
+        let horizontal = aspect_ratio * viewport_height * u;
+        let vertical = viewport_height * v;
+
+        OrthographicCamera {
+            lower_left_corner: origin - horizontal / 2.0 - vertical / 2.0,
+            horizontal,
+            vertical,
+            direction: -w,
+            shutter_duration,
+        }
+    }
+}
+
+impl Camera for OrthographicCamera {
+    fn ray_at(&self, screen_x: f64, screen_y: f64, rng: &mut dyn RngCore) -> Ray {
+        Ray::with_time(
+            self.lower_left_corner + screen_x * self.horizontal + screen_y * self.vertical,
+            self.direction,
+            sample_time(self.shutter_duration, rng),
+        )
+    }
+}
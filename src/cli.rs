@@ -0,0 +1,338 @@
+//! Command-line options for the renderer binary.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::format_err;
+use structopt::StructOpt;
+
+use ray_tracing_in_one_weekend::camera::{CameraSettings, PhysicalCamera, Projection};
+use ray_tracing_in_one_weekend::film::Aov;
+use ray_tracing_in_one_weekend::integrator::{DebugIntegrator, Integrator, PathTracer};
+use ray_tracing_in_one_weekend::scene::{SceneDescription, Sky};
+use ray_tracing_in_one_weekend::tonemap::{self, Operator, ToneMapper};
+use ray_tracing_in_one_weekend::vec3::{Color, Vec3};
+
+const DEFAULT_WIDTH: usize = 384;
+
+#[derive(Debug, StructOpt)]
+#[structopt(about = "Renders a scene with a path tracer and saves it as an image")]
+pub struct Options {
+    /// Name of the built-in scene to render
+    #[structopt(long, default_value = "two-perlin-spheres")]
+    pub scene: String,
+
+    /// Scene description file to render instead of a built-in scene
+    #[structopt(short = "f", long, parse(from_os_str))]
+    pub scene_file: Option<PathBuf>,
+
+    /// Image to write, whose format is chosen by its extension (.png, .ppm or .pfm)
+    #[structopt(short, long, default_value = "image.png", parse(from_os_str))]
+    pub output: PathBuf,
+
+    /// Auxiliary outputs to save next to the image, e.g. `image.albedo.png`, as a comma-separated
+    /// list of: albedo, normal, depth, position, object-id, material-id or sample-count
+    #[structopt(long = "aov", use_delimiter = true)]
+    pub aovs: Vec<Aov>,
+
+    /// Image width in pixels [default: set by the scene, or 384]
+    #[structopt(short, long, parse(try_from_str = parse_dimension))]
+    pub width: Option<usize>,
+
+    /// Image height in pixels [default: set by the scene, or derived from the aspect ratio]
+    #[structopt(long, parse(try_from_str = parse_dimension))]
+    pub height: Option<usize>,
+
+    /// Width divided by height, used when no explicit height is given [default: set by the
+    /// scene, or 16/9]
+    #[structopt(long, parse(try_from_str = parse_positive))]
+    pub aspect_ratio: Option<f64>,
+
+    /// Number of samples taken per pixel [default: set by the scene]
+    #[structopt(short, long, parse(try_from_str = parse_sample_count))]
+    pub samples_per_pixel: Option<u32>,
+
+    /// Maximum number of times a ray may bounce [default: set by the scene]
+    #[structopt(short = "d", long)]
+    pub max_bounce_depth: Option<u32>,
+
+    /// Number of bounces before paths carrying little light may be terminated at random
+    /// [default: set by the scene, or 3]
+    #[structopt(long)]
+    pub min_bounce_depth: Option<u32>,
+
+    /// Seed for all random sampling; equal seeds produce identical images [default: set by the
+    /// scene, or 0]
+    #[structopt(long)]
+    pub seed: Option<u64>,
+
+    /// Rendering algorithm: path, naive-path (without sampling lights directly), or one of the
+    /// debug views normals, uv, depth, albedo or hit-miss
+    #[structopt(short, long, default_value = "path", parse(try_from_str = parse_integrator))]
+    pub integrator: Box<dyn Integrator<Box<dyn Sky>>>,
+
+    /// Number of worker threads [default: one per logical CPU]
+    #[structopt(short = "j", long)]
+    pub threads: Option<usize>,
+
+    /// Exposure compensation in stops; each stop doubles the image brightness
+    #[structopt(short, long, default_value = "0", allow_hyphen_values = true)]
+    pub exposure: f64,
+
+    /// Tone mapping operator: clamp, reinhard, extended-reinhard or aces
+    #[structopt(short, long, default_value = "clamp")]
+    pub tone_map: Operator,
+
+    /// Luminance mapped to white by the extended-reinhard operator [default: the brightest
+    /// pixel]
+    #[structopt(long, parse(try_from_str = parse_positive))]
+    pub white_point: Option<f64>,
+
+    #[structopt(flatten)]
+    pub camera: CameraOptions,
+}
+
+impl Options {
+    /// Returns the image dimensions as `(width, height)`, falling back to the scene's own.
+    pub fn resolution(&self, description: &SceneDescription) -> (usize, usize) {
+        let width = self.width.or(description.width).unwrap_or(DEFAULT_WIDTH);
+        let aspect_ratio = self
+            .aspect_ratio
+            .unwrap_or_else(|| description.aspect_ratio());
+
+        // The scene's height only applies if neither its width nor aspect ratio was overridden.
+        let scene_height = match (self.width, self.aspect_ratio) {
+            (None, None) => description.height,
+            _ => None,
+        };
+        let height = self
+            .height
+            .or(scene_height)
+            .unwrap_or_else(|| (width as f64 / aspect_ratio) as usize);
+
+        (width.max(2), height.max(2))
+    }
+
+    /// Returns the post-processing applied to the rendered `pixels` before they are saved, where
+    /// `camera_exposure` is the camera's own exposure multiplier.
+    pub fn tone_mapper(&self, pixels: &[Color], camera_exposure: f64) -> ToneMapper {
+        let exposure = self.exposure + camera_exposure.log2();
+        let mapper = ToneMapper::new(self.tone_map).with_exposure(exposure);
+        let white_point = self.white_point.unwrap_or_else(|| {
+            let brightest = pixels
+                .iter()
+                .map(|&pixel| tonemap::luminance(pixel))
+                .fold(0.0, f64::max);
+            // Dim images keep their brightness rather than being stretched up to white.
+            (brightest * mapper.exposure_scale()).max(1.0)
+        });
+        mapper.with_white_point(white_point)
+    }
+}
+
+// Overrides for the scene's default camera. This is deliberately not a doc comment, since
+// structopt would otherwise use it as the about text of the whole program.
+#[derive(Debug, StructOpt)]
+pub struct CameraOptions {
+    /// Camera projection: perspective, orthographic, fisheye or equirectangular
+    #[structopt(long)]
+    pub projection: Option<Projection>,
+
+    /// Camera position, as `x,y,z`
+    #[structopt(long, allow_hyphen_values = true, parse(try_from_str = parse_vec3))]
+    pub look_from: Option<Vec3>,
+
+    /// Point the camera is aimed at, as `x,y,z`
+    #[structopt(long, allow_hyphen_values = true, parse(try_from_str = parse_vec3))]
+    pub look_at: Option<Vec3>,
+
+    /// Vertical field of view in degrees
+    #[structopt(long, parse(try_from_str = parse_fov))]
+    pub vfov: Option<f64>,
+
+    /// Lens diameter; zero gives a pinhole camera with everything in focus
+    #[structopt(long, parse(try_from_str = parse_aperture))]
+    pub aperture: Option<f64>,
+
+    /// Distance to the plane in perfect focus
+    #[structopt(long, parse(try_from_str = parse_positive))]
+    pub focus_dist: Option<f64>,
+
+    /// Time the shutter stays open in seconds, for motion blur and the exposure of physical
+    /// cameras
+    #[structopt(long, parse(try_from_str = parse_seconds))]
+    pub shutter: Option<Duration>,
+
+    /// Focal length in millimeters, which makes the camera physical
+    #[structopt(long)]
+    pub focal_length: Option<f64>,
+
+    /// Sensor size in millimeters, as `width,height`, which makes the camera physical
+    #[structopt(long, parse(try_from_str = parse_pair))]
+    pub sensor: Option<(f64, f64)>,
+
+    /// Ratio of focal length to aperture diameter, which makes the camera physical
+    #[structopt(long)]
+    pub f_stop: Option<f64>,
+
+    /// Film sensitivity, which makes the camera physical
+    #[structopt(long)]
+    pub iso: Option<f64>,
+}
+
+impl CameraOptions {
+    pub fn apply(&self, mut settings: CameraSettings) -> CameraSettings {
+        if let Some(projection) = self.projection {
+            settings.projection = projection;
+        }
+        if let Some(look_from) = self.look_from {
+            settings.look_from = look_from;
+        }
+        if let Some(look_at) = self.look_at {
+            settings.look_at = look_at;
+        }
+        if let Some(vfov) = self.vfov {
+            settings.vertical_fov_deg = vfov;
+        }
+        if let Some(aperture) = self.aperture {
+            settings.aperture = aperture;
+        }
+        if let Some(focus_dist) = self.focus_dist {
+            settings.focus_dist = Some(focus_dist);
+        }
+
+        let is_physical = self.focal_length.is_some()
+            || self.sensor.is_some()
+            || self.f_stop.is_some()
+            || self.iso.is_some();
+        if is_physical {
+            let physical = settings
+                .physical
+                .get_or_insert_with(PhysicalCamera::default);
+            if let Some(focal_length) = self.focal_length {
+                physical.focal_length_mm = focal_length;
+            }
+            if let Some(sensor_size) = self.sensor {
+                physical.sensor_size_mm = sensor_size;
+            }
+            if let Some(f_stop) = self.f_stop {
+                physical.f_stop = f_stop;
+            }
+            if let Some(iso) = self.iso {
+                physical.iso = iso;
+            }
+        }
+
+        if let Some(shutter) = self.shutter {
+            match &mut settings.physical {
+                Some(physical) => physical.shutter = shutter,
+                None => settings.shutter_duration = shutter,
+            }
+        }
+        settings
+    }
+}
+
+fn parse_integrator(s: &str) -> anyhow::Result<Box<dyn Integrator<Box<dyn Sky>>>> {
+    match s {
+        "path" => Ok(Box::new(PathTracer::default())),
+        "naive-path" => Ok(Box::new(PathTracer::default().with_light_sampling(false))),
+        _ => match s.parse::<DebugIntegrator>() {
+            Ok(debug) => Ok(Box::new(debug)),
+            Err(_) => Err(format_err!(
+                "Unknown integrator `{}`, expected one of: path, naive-path, normals, uv, \
+                 depth, albedo, hit-miss",
+                s
+            )),
+        },
+    }
+}
+
+fn parse_sample_count(s: &str) -> Result<u32, String> {
+    match s.parse::<u32>() {
+        Ok(0) => Err(String::from("at least one sample per pixel is needed")),
+        Ok(count) => Ok(count),
+        Err(e) => Err(format!("invalid sample count `{}`: {}", s, e)),
+    }
+}
+
+fn parse_dimension(s: &str) -> Result<usize, String> {
+    match s.parse::<usize>() {
+        Ok(pixels) if pixels >= 2 => Ok(pixels),
+        Ok(_) => Err(String::from(
+            "images need at least two pixels along each side",
+        )),
+        Err(e) => Err(format!("invalid pixel count `{}`: {}", s, e)),
+    }
+}
+
+/// Parses a finite number, which `is_valid` must also accept.
+fn parse_number<F: Fn(f64) -> bool>(s: &str, is_valid: F, expected: &str) -> Result<f64, String> {
+    let value = s
+        .parse::<f64>()
+        .map_err(|e| format!("invalid number `{}`: {}", s, e))?;
+    if value.is_finite() && is_valid(value) {
+        Ok(value)
+    } else {
+        Err(format!("expected {}, found `{}`", expected, s))
+    }
+}
+
+fn parse_positive(s: &str) -> Result<f64, String> {
+    parse_number(s, |value| value > 0.0, "a positive number")
+}
+
+fn parse_fov(s: &str) -> Result<f64, String> {
+    parse_number(
+        s,
+        |degrees| degrees > 0.0 && degrees <= 360.0,
+        "an angle above 0 and up to 360 degrees",
+    )
+}
+
+fn parse_aperture(s: &str) -> Result<f64, String> {
+    parse_number(
+        s,
+        |diameter| diameter >= 0.0,
+        "a non-negative lens diameter",
+    )
+}
+
+fn parse_seconds(s: &str) -> Result<Duration, String> {
+    let seconds = s
+        .parse::<f64>()
+        .map_err(|e| format!("invalid number `{}`: {}", s, e))?;
+    Duration::try_from_secs_f64(seconds).map_err(|_| {
+        format!(
+            "expected a finite, non-negative number of seconds, found `{}`",
+            s
+        )
+    })
+}
+
+fn parse_numbers(s: &str) -> Result<Vec<f64>, String> {
+    s.split(',')
+        .map(|c| c.trim().parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("invalid numbers `{}`: {}", s, e))
+}
+
+fn parse_vec3(s: &str) -> Result<Vec3, String> {
+    match parse_numbers(s)?[..] {
+        [x, y, z] => Ok(Vec3::new(x, y, z)),
+        _ => Err(format!(
+            "expected three comma-separated numbers, found `{}`",
+            s
+        )),
+    }
+}
+
+fn parse_pair(s: &str) -> Result<(f64, f64), String> {
+    match parse_numbers(s)?[..] {
+        [a, b] => Ok((a, b)),
+        _ => Err(format!(
+            "expected two comma-separated numbers, found `{}`",
+            s
+        )),
+    }
+}
@@ -0,0 +1,243 @@
+//! The multi-channel output of `render::render()`: the rendered image along with auxiliary
+//! buffers (AOVs) describing what each pixel sees, for compositing and denoising.
+
+use std::str::FromStr;
+
+use anyhow::format_err;
+
+use crate::random;
+use crate::vec3::{Color, Point3, Vec3};
+
+/// An auxiliary output variable, i.e. one of the buffers a `Film` holds besides the image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Aov {
+    Albedo,
+    Normal,
+    Depth,
+    Position,
+    ObjectId,
+    MaterialId,
+    SampleCount,
+}
+
+impl Aov {
+    pub const ALL: [Aov; 7] = [
+        Aov::Albedo,
+        Aov::Normal,
+        Aov::Depth,
+        Aov::Position,
+        Aov::ObjectId,
+        Aov::MaterialId,
+        Aov::SampleCount,
+    ];
+
+    /// Returns the name the AOV is parsed from, which also tags the files it is saved to.
+    pub fn name(self) -> &'static str {
+        match self {
+            Aov::Albedo => "albedo",
+            Aov::Normal => "normal",
+            Aov::Depth => "depth",
+            Aov::Position => "position",
+            Aov::ObjectId => "object-id",
+            Aov::MaterialId => "material-id",
+            Aov::SampleCount => "sample-count",
+        }
+    }
+}
+
+impl FromStr for Aov {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Aov::ALL
+            .iter()
+            .copied()
+            .find(|aov| aov.name() == s)
+            .ok_or_else(|| {
+                format_err!(
+                    "Unknown AOV `{}`, expected one of: albedo, normal, depth, position, \
+                     object-id, material-id, sample-count",
+                    s
+                )
+            })
+    }
+}
+
+/// A rendered image along with its AOVs, each stored in row-major order starting from the
+/// top-left corner.
+///
+/// The surface AOVs describe the first surface hit by each camera ray. Those that can be averaged
+/// hold the mean over a pixel's samples, while the IDs are taken from its first sample that hits
+/// anything. Pixels where no sample hit anything are zero in every AOV.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Film {
+    pub width: usize,
+    pub height: usize,
+    /// The linear radiance estimated by the integrator.
+    pub beauty: Vec<Color>,
+    /// The surface color given by `Material::albedo()`, counting misses as black.
+    pub albedo: Vec<Color>,
+    /// The shading normal facing the camera, counting misses as zero, so it isn't normalized.
+    pub normal: Vec<Vec3>,
+    /// The distance from the camera in world units, averaged over the samples that hit anything.
+    pub depth: Vec<f64>,
+    /// The point in world space, averaged over the samples that hit anything.
+    pub position: Vec<Point3>,
+    /// One more than the index of the object in `Scene::world`, so that misses are zero.
+    ///
+    /// This identifies top-level objects only, so everything inside the same `Bvh`, mesh or
+    /// `Transform` shares one ID.
+    pub object_id: Vec<u32>,
+    /// Materials numbered from one in the order they first appear in the image.
+    pub material_id: Vec<u32>,
+    /// The number of samples the pixel's values are made up of.
+    pub sample_count: Vec<u32>,
+}
+
+impl Film {
+    /// Creates a film of the given size with every value set to zero.
+    pub fn new(width: usize, height: usize) -> Self {
+        let len = width * height;
+        Film {
+            width,
+            height,
+            beauty: vec![Color::zeros(); len],
+            albedo: vec![Color::zeros(); len],
+            normal: vec![Vec3::zeros(); len],
+            depth: vec![0.0; len],
+            position: vec![Point3::zeros(); len],
+            object_id: vec![0; len],
+            material_id: vec![0; len],
+            sample_count: vec![0; len],
+        }
+    }
+
+    /// Returns the raw values of an AOV as colors, with scalars repeated across all channels.
+    pub fn channel(&self, aov: Aov) -> Vec<Color> {
+        let grey = |value: f64| Color::new(value, value, value);
+        match aov {
+            Aov::Albedo => self.albedo.clone(),
+            Aov::Normal => self.normal.clone(),
+            Aov::Depth => self.depth.iter().map(|&depth| grey(depth)).collect(),
+            Aov::Position => self.position.clone(),
+            Aov::ObjectId => self.object_id.iter().map(|&id| grey(id as f64)).collect(),
+            Aov::MaterialId => self.material_id.iter().map(|&id| grey(id as f64)).collect(),
+            Aov::SampleCount => self
+                .sample_count
+                .iter()
+                .map(|&count| grey(count as f64))
+                .collect(),
+        }
+    }
+
+    /// Returns an AOV mapped into `0.0..=1.0` for viewing, since raw values would mostly clip.
+    ///
+    /// Normals are mapped like `DebugIntegrator::Normals`, depths and sample counts are divided by
+    /// their maximum, positions are scaled to the bounds of all hits, and each ID gets a random
+    /// color.
+    pub fn preview(&self, aov: Aov) -> Vec<Color> {
+        let is_hit = |index: usize| self.depth[index] > 0.0;
+        match aov {
+            Aov::Albedo => self.albedo.clone(),
+            Aov::Normal => (0..self.normal.len())
+                .map(|index| {
+                    if is_hit(index) {
+                        0.5 * (self.normal[index] + Color::ones())
+                    } else {
+                        Color::zeros()
+                    }
+                })
+                .collect(),
+            Aov::Depth | Aov::SampleCount => {
+                let values = self.channel(aov);
+                let max = values.iter().fold(0.0, |max: f64, value| max.max(value.x));
+                if max <= 0.0 {
+                    return values;
+                }
+                values.into_iter().map(|value| value / max).collect()
+            }
+            Aov::Position => {
+                let hits = (0..self.position.len()).filter(|&index| is_hit(index));
+                let far = Point3::new(f64::MAX, f64::MAX, f64::MAX);
+                let (min, max) = hits.fold((far, -far), |(min, max), index| {
+                    let p = self.position[index];
+                    (
+                        Point3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z)),
+                        Point3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z)),
+                    )
+                });
+                let scale = |value: f64, min: f64, max: f64| {
+                    if max > min {
+                        (value - min) / (max - min)
+                    } else {
+                        0.5
+                    }
+                };
+                (0..self.position.len())
+                    .map(|index| {
+                        if !is_hit(index) {
+                            return Color::zeros();
+                        }
+                        let p = self.position[index];
+                        Color::new(
+                            scale(p.x, min.x, max.x),
+                            scale(p.y, min.y, max.y),
+                            scale(p.z, min.z, max.z),
+                        )
+                    })
+                    .collect()
+            }
+            Aov::ObjectId => self.object_id.iter().map(|&id| id_color(id)).collect(),
+            Aov::MaterialId => self.material_id.iter().map(|&id| id_color(id)).collect(),
+        }
+    }
+}
+
+/// Picks a random but fixed color for an ID, keeping zero black.
+fn id_color(id: u32) -> Color {
+    if id == 0 {
+        return Color::zeros();
+    }
+    let hash = random::mix(0, id as u64);
+    let channel = |shift: u32| 0.2 + 0.8 * ((hash >> shift) & 0xff) as f64 / 255.0;
+    Color::new(channel(0), channel(8), channel(16))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_aov_names() {
+        for &aov in &Aov::ALL {
+            assert_eq!(aov.name().parse::<Aov>().unwrap(), aov);
+        }
+        assert!("beauty".parse::<Aov>().is_err());
+    }
+
+    #[test]
+    fn previews_stay_in_range_and_keep_misses_black() {
+        let mut film = Film::new(2, 1);
+        film.normal[0] = Vec3::new(0.0, 0.0, -1.0);
+        film.depth[0] = 8.0;
+        film.position[0] = Point3::new(-3.0, 5.0, 100.0);
+        film.object_id[0] = 7;
+        film.material_id[0] = 1;
+        film.sample_count = vec![4, 4];
+
+        for &aov in &Aov::ALL {
+            let preview = film.preview(aov);
+            for pixel in &preview {
+                for &channel in &[pixel.x, pixel.y, pixel.z] {
+                    assert!((0.0..=1.0).contains(&channel), "{:?}: {}", aov, pixel);
+                }
+            }
+            if aov != Aov::SampleCount {
+                assert_eq!(preview[1], Color::zeros(), "{:?}", aov);
+            }
+        }
+        assert_eq!(film.preview(Aov::Normal)[0], Color::new(0.5, 0.5, 0.0));
+        assert_eq!(film.preview(Aov::Depth)[0], Color::ones());
+        assert_eq!(film.channel(Aov::ObjectId)[0], Color::new(7.0, 7.0, 7.0));
+    }
+}
@@ -1,7 +1,16 @@
-pub use self::bvh::Bvh;
+pub use self::bvh::{Bvh, BvhOptions, SplitMethod};
+pub use self::linear_bvh::LinearBvh;
+pub use self::medium::ConstantMedium;
+pub use self::mesh::{Face, TriangleMesh};
+pub use self::rect::{Cuboid, FlipFace, XyRect, XzRect, YzRect};
 pub use self::sphere::{MovingSphere, Sphere};
+pub use self::transform::{Affine, Transform};
+pub use self::triangle::Triangle;
 
 use std::fmt::Debug;
+use std::sync::Arc;
+
+use rand::{Rng, RngCore};
 
 use crate::aabb::{self, Aabb};
 use crate::mat::Material;
@@ -9,26 +18,98 @@ use crate::ray::Ray;
 use crate::vec3::{Point3, Vec3};
 
 mod bvh;
+mod linear_bvh;
+mod medium;
+mod mesh;
+mod obj;
+mod rect;
 mod sphere;
+mod transform;
+mod triangle;
+
+/// The `t_range` of the rays traced towards objects to evaluate their sampling densities.
+const SAMPLING_T_RANGE: (f64, f64) = (0.001, f64::MAX);
 
 pub trait Hittable: Debug + Send + Sync {
-    fn hit(&self, ray: &Ray, t_range: (f64, f64)) -> Option<HitRecord>;
+    fn hit(&self, ray: &Ray, t_range: (f64, f64)) -> Option<HitRecord<'_>>;
     fn bounding_box(&self, time0: f64, time1: f64) -> Option<Aabb>;
+
+    /// Returns whether anything blocks `ray` within `t_range`, e.g. for shadow rays.
+    ///
+    /// Unlike `hit()`, this may stop at the first intersection found rather than the closest one,
+    /// and never builds a `HitRecord`. The default implementation simply defers to `hit()`.
+    fn occluded(&self, ray: &Ray, t_range: (f64, f64)) -> bool {
+        self.hit(ray, t_range).is_some()
+    }
+
+    /// Returns the probability density, per unit of solid angle, with which `sample_direction()`
+    /// picks `direction` from `origin`.
+    ///
+    /// This is zero for directions that miss the object, and for objects that can't be sampled.
+    fn pdf_value(&self, _origin: Point3, _direction: Vec3) -> f64 {
+        0.0
+    }
+
+    /// Picks a direction from `origin` towards a random point on the object, so that it can be
+    /// sampled as a light, or returns `None` if the object doesn't support this.
+    ///
+    /// The direction need not be of unit length.
+    fn sample_direction(&self, _origin: Point3, _rng: &mut dyn RngCore) -> Option<Vec3> {
+        None
+    }
+}
+
+/// Finds the closest hit among `objects`, along with the index of the object that was hit.
+pub fn closest_hit<'a>(
+    objects: &'a [Box<dyn Hittable>],
+    ray: &Ray,
+    (t_min, t_max): (f64, f64),
+) -> Option<(usize, HitRecord<'a>)> {
+    let mut closest_so_far = None;
+    let mut t_max = t_max;
+
+    for (index, object) in objects.iter().enumerate() {
+        if let Some(record) = object.hit(ray, (t_min, t_max)) {
+            t_max = record.t;
+            closest_so_far = Some((index, record));
+        }
+    }
+
+    closest_so_far
 }
 
 impl<T: AsRef<[Box<dyn Hittable>]> + Debug + Send + Sync> Hittable for T {
-    fn hit(&self, ray: &Ray, (t_min, t_max): (f64, f64)) -> Option<HitRecord> {
-        let mut closest_so_far: Option<HitRecord> = None;
-        let mut t_max = t_max;
+    fn hit(&self, ray: &Ray, t_range: (f64, f64)) -> Option<HitRecord<'_>> {
+        closest_hit(self.as_ref(), ray, t_range).map(|(_, record)| record)
+    }
 
-        for object in self.as_ref().iter() {
-            if let Some(record) = object.hit(ray, (t_min, t_max)) {
-                t_max = record.t;
-                closest_so_far = Some(record);
-            }
+    fn occluded(&self, ray: &Ray, t_range: (f64, f64)) -> bool {
+        self.as_ref()
+            .iter()
+            .any(|object| object.occluded(ray, t_range))
+    }
+
+    /// Returns the mean density of the objects, since `sample_direction()` picks one uniformly.
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let objects = self.as_ref();
+        if objects.is_empty() {
+            return 0.0;
+        }
+
+        let sum: f64 = objects
+            .iter()
+            .map(|object| object.pdf_value(origin, direction))
+            .sum();
+        sum / objects.len() as f64
+    }
+
+    fn sample_direction(&self, origin: Point3, rng: &mut dyn RngCore) -> Option<Vec3> {
+        let objects = self.as_ref();
+        if objects.is_empty() {
+            return None;
         }
 
-        closest_so_far
+        objects[rng.gen_range(0, objects.len())].sample_direction(origin, rng)
     }
 
     fn bounding_box(&self, time0: f64, time1: f64) -> Option<Aabb> {
@@ -52,6 +133,42 @@ impl<T: AsRef<[Box<dyn Hittable>]> + Debug + Send + Sync> Hittable for T {
     }
 }
 
+/// An object that can be placed in several lists at once, such as both the world and the scene's
+/// lights, without building it twice.
+///
+/// This wraps an `Arc` rather than implementing `Hittable` for `Arc<dyn Hittable>` itself, which
+/// would overlap with the implementation for lists.
+#[derive(Debug)]
+pub struct Shared(Arc<dyn Hittable>);
+
+impl Shared {
+    pub fn new<H: Into<Arc<dyn Hittable>>>(inner: H) -> Self {
+        Shared(inner.into())
+    }
+}
+
+impl Hittable for Shared {
+    fn hit(&self, ray: &Ray, t_range: (f64, f64)) -> Option<HitRecord<'_>> {
+        self.0.hit(ray, t_range)
+    }
+
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<Aabb> {
+        self.0.bounding_box(time0, time1)
+    }
+
+    fn occluded(&self, ray: &Ray, t_range: (f64, f64)) -> bool {
+        self.0.occluded(ray, t_range)
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        self.0.pdf_value(origin, direction)
+    }
+
+    fn sample_direction(&self, origin: Point3, rng: &mut dyn RngCore) -> Option<Vec3> {
+        self.0.sample_direction(origin, rng)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct HitRecord<'a> {
     pub point: Point3,
@@ -173,6 +290,16 @@ mod tests {
         assert!((-0.50002..-0.50001).contains(&hit_record.point.z));
     }
 
+    #[test]
+    fn occlusion_respects_t_range() {
+        let world = generate_world();
+        let center_of_image = compute_ray(IMAGE_WIDTH / 2, IMAGE_HEIGHT / 2);
+
+        assert!(world.occluded(&center_of_image, (0.0, f64::MAX)));
+        assert!(world.occluded(&center_of_image, (0.0, 0.6)));
+        assert!(!world.occluded(&center_of_image, (0.0, 0.4)));
+    }
+
     #[test]
     fn returns_none_if_hits_nothing() {
         let world = generate_world();
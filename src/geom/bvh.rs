@@ -6,79 +6,173 @@ use rayon::slice::ParallelSliceMut;
 
 use super::{HitRecord, Hittable};
 use crate::aabb::{self, Aabb};
+use crate::random;
 use crate::ray::Ray;
+use crate::vec3::Point3;
 
 const MAX_SEQUENTIAL: usize = 250;
 
+/// Strategy used to partition objects when building a `Bvh`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SplitMethod {
+    /// Minimizes the estimated traversal cost using the binned surface area heuristic.
+    Sah,
+    /// Sorts along a random axis and splits the objects into two equally sized halves.
+    Median,
+}
+
+/// Parameters controlling how a `Bvh` is built.
+///
+/// Larger bin counts and smaller leaves produce better trees for big scenes at the expense of
+/// longer build times.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BvhOptions {
+    pub split_method: SplitMethod,
+    /// Number of buckets each axis is divided into when evaluating SAH split candidates.
+    pub bin_count: usize,
+    /// Largest number of objects a leaf may hold.
+    pub max_leaf_size: usize,
+    /// Cost of traversing a branch relative to intersecting a single object.
+    pub traversal_cost: f64,
+    /// Seeds the random choices made by `SplitMethod::Median`.
+    pub seed: u64,
+}
+
+impl BvhOptions {
+    pub fn with_split_method(mut self, val: SplitMethod) -> Self {
+        self.split_method = val;
+        self
+    }
+
+    pub fn with_bin_count(mut self, val: usize) -> Self {
+        self.bin_count = val.max(2);
+        self
+    }
+
+    pub fn with_max_leaf_size(mut self, val: usize) -> Self {
+        self.max_leaf_size = val.max(1);
+        self
+    }
+
+    pub fn with_traversal_cost(mut self, val: f64) -> Self {
+        self.traversal_cost = val;
+        self
+    }
+
+    pub fn with_seed(mut self, val: u64) -> Self {
+        self.seed = val;
+        self
+    }
+}
+
+impl Default for BvhOptions {
+    fn default() -> Self {
+        BvhOptions {
+            split_method: SplitMethod::Sah,
+            bin_count: 16,
+            max_leaf_size: 4,
+            traversal_cost: 0.125,
+            seed: 0,
+        }
+    }
+}
+
 #[derive(Debug)]
-enum Node {
-    Branch { left: Box<Bvh>, right: Box<Bvh> },
-    Leaf(Box<dyn Hittable>),
+pub(super) enum Node {
+    Branch {
+        left: Box<BvhNode>,
+        right: Box<BvhNode>,
+    },
+    Leaf(Vec<Box<dyn Hittable>>),
 }
 
+#[derive(Debug)]
+pub(super) struct BvhNode {
+    pub(super) tree: Node,
+    pub(super) bounding_box: Aabb,
+}
+
+/// A bounding volume hierarchy over the objects of a scene.
+///
+/// Objects without a bounding box over the requested time range, such as infinite planes,
+/// cannot be placed in the tree and are instead tested alongside it on every ray.
 #[derive(Debug)]
 pub struct Bvh {
-    tree: Node,
-    bounding_box: Aabb,
+    pub(super) root: Option<BvhNode>,
+    pub(super) unbounded: Vec<Box<dyn Hittable>>,
 }
 
 impl Bvh {
-    pub fn new(mut world: Vec<Box<dyn Hittable>>, time0: f64, time1: f64) -> anyhow::Result<Self> {
-        #[inline]
-        fn box_compare(axis: usize) -> impl Fn(&Box<dyn Hittable>, &Box<dyn Hittable>) -> Ordering {
-            move |left, right| match (left.bounding_box(0.0, 0.0), right.bounding_box(0.0, 0.0)) {
-                (Some(left), Some(right)) => left.min[axis].partial_cmp(&right.min[axis]).unwrap(),
-                _ => panic!("No bounding box in Bvh::new() constructor"),
-            }
-        }
-
-        let axis = rand::thread_rng().gen_range(0, 3);
-        world.par_sort_unstable_by(box_compare(axis));
+    pub fn new(world: Vec<Box<dyn Hittable>>, time0: f64, time1: f64) -> anyhow::Result<Self> {
+        Bvh::with_options(world, time0, time1, BvhOptions::default())
+    }
 
-        match world.len() {
-            0 => Err(format_err!("Scene cannot be empty")),
-            1 => {
-                let leaf = world.remove(0);
-                let bounding_box = leaf
-                    .bounding_box(time0, time1)
-                    .ok_or_else(|| format_err!("Element is missing bounding box"))?;
+    /// Builds a hierarchy fitting the objects as they move between `time0` and `time1`.
+    pub fn with_options(
+        world: Vec<Box<dyn Hittable>>,
+        time0: f64,
+        time1: f64,
+        options: BvhOptions,
+    ) -> anyhow::Result<Self> {
+        if world.is_empty() {
+            return Err(format_err!("Scene cannot be empty"));
+        }
 
-                Ok(Bvh {
+        let mut primitives = Vec::with_capacity(world.len());
+        let mut unbounded = Vec::new();
+        for object in world {
+            match object.bounding_box(time0, time1) {
+                Some(bounding_box) => primitives.push(Primitive {
+                    centroid: bounding_box.centroid(),
                     bounding_box,
-                    tree: Node::Leaf(leaf),
-                })
-            }
-            len => {
-                let half = world.drain(len / 2..).collect();
-                let (right, left) = if len < MAX_SEQUENTIAL {
-                    let right = Bvh::new(half, time0, time1)?;
-                    let left = Bvh::new(world, time0, time1)?;
-                    (right, left)
-                } else {
-                    let (right, left) = rayon::join(
-                        || Bvh::new(half, time0, time1),
-                        || Bvh::new(world, time0, time1),
-                    );
-                    (right?, left?)
-                };
-
-                Ok(Bvh {
-                    bounding_box: aabb::surrounding_box(left.bounding_box, right.bounding_box),
-                    tree: Node::Branch {
-                        left: Box::new(left),
-                        right: Box::new(right),
-                    },
-                })
+                    object,
+                }),
+                None => unbounded.push(object),
             }
         }
+
+        let root = if primitives.is_empty() {
+            None
+        } else {
+            Some(build(primitives, &options, options.seed))
+        };
+
+        Ok(Bvh { root, unbounded })
     }
 }
 
 impl Hittable for Bvh {
-    fn hit(&self, ray: &Ray, t_range: (f64, f64)) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, (t_min, t_max): (f64, f64)) -> Option<HitRecord<'_>> {
+        let hit_unbounded = self.unbounded.hit(ray, (t_min, t_max));
+        let t_max = hit_unbounded.as_ref().map(|hit| hit.t).unwrap_or(t_max);
+        let hit_tree = self
+            .root
+            .as_ref()
+            .and_then(|root| root.hit(ray, (t_min, t_max)));
+        hit_tree.or(hit_unbounded)
+    }
+
+    fn occluded(&self, ray: &Ray, t_range: (f64, f64)) -> bool {
+        match &self.root {
+            Some(root) => self.unbounded.occluded(ray, t_range) || root.occluded(ray, t_range),
+            None => self.unbounded.occluded(ray, t_range),
+        }
+    }
+
+    fn bounding_box(&self, _: f64, _: f64) -> Option<Aabb> {
+        if self.unbounded.is_empty() {
+            self.root.as_ref().map(|root| root.bounding_box)
+        } else {
+            None
+        }
+    }
+}
+
+impl BvhNode {
+    fn hit(&self, ray: &Ray, t_range: (f64, f64)) -> Option<HitRecord<'_>> {
         if self.bounding_box.hit(ray, t_range) {
             match &self.tree {
-                Node::Leaf(object) => object.hit(ray, t_range),
+                Node::Leaf(objects) => objects.hit(ray, t_range),
                 Node::Branch { left, right } => {
                     let hit_left = left.hit(ray, t_range);
                     let hit_right = {
@@ -95,7 +189,346 @@ impl Hittable for Bvh {
         }
     }
 
-    fn bounding_box(&self, _: f64, _: f64) -> Option<Aabb> {
-        Some(self.bounding_box)
+    fn occluded(&self, ray: &Ray, t_range: (f64, f64)) -> bool {
+        if !self.bounding_box.hit(ray, t_range) {
+            return false;
+        }
+
+        match &self.tree {
+            Node::Leaf(objects) => objects.occluded(ray, t_range),
+            Node::Branch { left, right } => {
+                left.occluded(ray, t_range) || right.occluded(ray, t_range)
+            }
+        }
+    }
+}
+
+/// An object awaiting placement in the tree, along with its precomputed bounds.
+#[derive(Debug)]
+struct Primitive {
+    bounding_box: Aabb,
+    centroid: Point3,
+    object: Box<dyn Hittable>,
+}
+
+/// Builds a subtree, deriving the seeds of its children from `seed` so that the result does not
+/// depend on how the work is split across threads.
+fn build(mut primitives: Vec<Primitive>, options: &BvhOptions, seed: u64) -> BvhNode {
+    let bounding_box = primitives
+        .iter()
+        .map(|p| p.bounding_box)
+        .fold(primitives[0].bounding_box, aabb::surrounding_box);
+
+    let split = match options.split_method {
+        SplitMethod::Sah => sah_split(&mut primitives, bounding_box, options),
+        SplitMethod::Median => median_split(&mut primitives, options, seed),
+    };
+
+    let len = primitives.len();
+    let mid = match split {
+        Some(mid) if mid > 0 && mid < len => mid,
+        _ => {
+            let objects = primitives.into_iter().map(|p| p.object).collect();
+            return BvhNode {
+                tree: Node::Leaf(objects),
+                bounding_box,
+            };
+        }
+    };
+
+    let half = primitives.split_off(mid);
+    let (left_seed, right_seed) = (random::mix(seed, 1), random::mix(seed, 2));
+    let (left, right) = if len < MAX_SEQUENTIAL {
+        (
+            build(primitives, options, left_seed),
+            build(half, options, right_seed),
+        )
+    } else {
+        rayon::join(
+            || build(primitives, options, left_seed),
+            || build(half, options, right_seed),
+        )
+    };
+
+    BvhNode {
+        tree: Node::Branch {
+            left: Box::new(left),
+            right: Box::new(right),
+        },
+        bounding_box,
+    }
+}
+
+/// Sorts the primitives along a random axis and returns the index of the median.
+fn median_split(primitives: &mut [Primitive], options: &BvhOptions, seed: u64) -> Option<usize> {
+    if primitives.len() <= options.max_leaf_size {
+        return None;
+    }
+
+    let axis = random::stream_rng(seed, 0).gen_range(0, 3);
+    primitives.par_sort_unstable_by(|left, right| {
+        left.bounding_box.min[axis]
+            .partial_cmp(&right.bounding_box.min[axis])
+            .unwrap_or(Ordering::Equal)
+    });
+
+    Some(primitives.len() / 2)
+}
+
+/// Partitions the primitives along the cheapest binned SAH split plane and returns the index of
+/// the first primitive on the far side, or `None` if a leaf would be cheaper.
+fn sah_split(
+    primitives: &mut [Primitive],
+    bounding_box: Aabb,
+    options: &BvhOptions,
+) -> Option<usize> {
+    #[derive(Clone, Copy, Default)]
+    struct Bin {
+        count: usize,
+        bounds: Option<Aabb>,
+    }
+
+    fn merge(a: Option<Aabb>, b: Option<Aabb>) -> Option<Aabb> {
+        match (a, b) {
+            (Some(a), Some(b)) => Some(aabb::surrounding_box(a, b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    fn bin_index(value: f64, (lo, hi): (f64, f64), bin_count: usize) -> usize {
+        let offset = (value - lo) / (hi - lo);
+        ((offset * bin_count as f64) as usize).min(bin_count - 1)
+    }
+
+    let len = primitives.len();
+    if len == 1 {
+        return None;
+    }
+
+    let centroid_bounds = primitives
+        .iter()
+        .map(|p| Aabb::new(p.centroid, p.centroid))
+        .fold(
+            Aabb::new(primitives[0].centroid, primitives[0].centroid),
+            aabb::surrounding_box,
+        );
+
+    let bin_count = options.bin_count.max(2);
+    let parent_area = bounding_box.surface_area();
+    let mut best: Option<(f64, usize, usize)> = None;
+
+    for axis in 0..3 {
+        let extent = (centroid_bounds.min[axis], centroid_bounds.max[axis]);
+        if extent.1 - extent.0 <= 0.0 {
+            continue;
+        }
+
+        let mut bins = vec![Bin::default(); bin_count];
+        for p in primitives.iter() {
+            let bin = &mut bins[bin_index(p.centroid[axis], extent, bin_count)];
+            bin.count += 1;
+            bin.bounds = merge(bin.bounds, Some(p.bounding_box));
+        }
+
+        // Sweep from the right to find the area and count of everything past each split plane.
+        let mut right_costs = vec![0.0; bin_count];
+        let (mut right_bounds, mut right_count) = (None, 0);
+        for i in (1..bin_count).rev() {
+            right_bounds = merge(right_bounds, bins[i].bounds);
+            right_count += bins[i].count;
+            right_costs[i] = right_bounds.map_or(0.0, |b| b.surface_area()) * right_count as f64;
+        }
+
+        let (mut left_bounds, mut left_count) = (None, 0);
+        for split in 1..bin_count {
+            left_bounds = merge(left_bounds, bins[split - 1].bounds);
+            left_count += bins[split - 1].count;
+            if left_count == 0 || left_count == len {
+                continue;
+            }
+
+            let left_cost = left_bounds.map_or(0.0, |b| b.surface_area()) * left_count as f64;
+            let cost = options.traversal_cost + (left_cost + right_costs[split]) / parent_area;
+            match best {
+                Some((best_cost, _, _)) if best_cost <= cost => {}
+                _ => best = Some((cost, axis, split)),
+            }
+        }
+    }
+
+    match best {
+        Some((cost, axis, split)) => {
+            if len <= options.max_leaf_size && cost >= len as f64 {
+                return None;
+            }
+
+            let extent = (centroid_bounds.min[axis], centroid_bounds.max[axis]);
+            let mid = partition(primitives, |p| {
+                bin_index(p.centroid[axis], extent, bin_count) < split
+            });
+            Some(mid)
+        }
+        // Every centroid coincides, so no plane can separate them; fall back to splitting evenly.
+        None if len > options.max_leaf_size => Some(len / 2),
+        None => None,
+    }
+}
+
+/// Moves every element satisfying `pred` to the front of the slice, returning how many there are.
+fn partition<T, F: Fn(&T) -> bool>(items: &mut [T], pred: F) -> usize {
+    let mut first_false = 0;
+    for i in 0..items.len() {
+        if pred(&items[i]) {
+            items.swap(i, first_false);
+            first_false += 1;
+        }
+    }
+    first_false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geom::{LinearBvh, Sphere};
+    use crate::mat::{Lambertian, Material};
+    use crate::vec3::{Color, Vec3};
+
+    /// The infinite plane `y = 0`, which has no bounding box.
+    #[derive(Debug, Default)]
+    struct Ground(Lambertian<Color>);
+
+    impl Hittable for Ground {
+        fn hit(&self, ray: &Ray, (t_min, t_max): (f64, f64)) -> Option<HitRecord<'_>> {
+            let t = -ray.origin.y / ray.direction.y;
+            if !(t > t_min && t < t_max) {
+                return None;
+            }
+
+            let material: &dyn Material = &self.0;
+            let normal = Vec3::new(0.0, 1.0, 0.0);
+            let point = ray.point_at(t);
+            Some(HitRecord::with_face_normal(
+                *ray, point, normal, material, 0.0, 0.0, t,
+            ))
+        }
+
+        fn bounding_box(&self, _: f64, _: f64) -> Option<Aabb> {
+            None
+        }
+    }
+
+    fn row_of_spheres(count: usize) -> Vec<Box<dyn Hittable>> {
+        (0..count)
+            .map(|i| {
+                let center = Point3::new(i as f64 * 3.0, 0.0, 0.0);
+                Box::new(Sphere::new(center, 1.0, Lambertian::default())) as Box<dyn Hittable>
+            })
+            .collect()
+    }
+
+    fn depth(node: &BvhNode) -> usize {
+        match &node.tree {
+            Node::Leaf(_) => 1,
+            Node::Branch { left, right } => 1 + depth(left).max(depth(right)),
+        }
+    }
+
+    fn leaf_sizes(node: &BvhNode, sizes: &mut Vec<usize>) {
+        match &node.tree {
+            Node::Leaf(objects) => sizes.push(objects.len()),
+            Node::Branch { left, right } => {
+                leaf_sizes(left, sizes);
+                leaf_sizes(right, sizes);
+            }
+        }
+    }
+
+    #[test]
+    fn split_methods_find_same_hits() {
+        let options = BvhOptions::default();
+        let sah = Bvh::with_options(row_of_spheres(100), 0.0, 0.0, options).unwrap();
+        let median = options.with_split_method(SplitMethod::Median);
+        let median = Bvh::with_options(row_of_spheres(100), 0.0, 0.0, median).unwrap();
+
+        for i in 0..100 {
+            let ray = Ray::new(
+                Point3::new(i as f64 * 3.0, 5.0, 0.0),
+                Vec3::new(0.0, -1.0, 0.0),
+            );
+            let sah_hit = sah.hit(&ray, (0.0, f64::MAX)).expect("SAH tree missed");
+            let median_hit = median
+                .hit(&ray, (0.0, f64::MAX))
+                .expect("median tree missed");
+            assert_eq!(sah_hit.point, median_hit.point);
+            assert_eq!(sah_hit.t, 4.0);
+        }
+    }
+
+    #[test]
+    fn respects_max_leaf_size() {
+        for &max_leaf_size in &[1, 3, 8] {
+            let options = BvhOptions::default().with_max_leaf_size(max_leaf_size);
+            let bvh = Bvh::with_options(row_of_spheres(64), 0.0, 0.0, options).unwrap();
+
+            let mut sizes = Vec::new();
+            leaf_sizes(bvh.root.as_ref().unwrap(), &mut sizes);
+            assert_eq!(sizes.iter().sum::<usize>(), 64);
+            assert!(sizes.iter().all(|&size| size <= max_leaf_size));
+        }
+    }
+
+    #[test]
+    fn sah_separates_distant_cluster() {
+        let mut world = row_of_spheres(32);
+        let outlier = Sphere::new(Point3::new(1e4, 0.0, 0.0), 1.0, Lambertian::default());
+        world.push(Box::new(outlier));
+
+        let bvh = Bvh::new(world, 0.0, 0.0).unwrap();
+        let root = bvh.root.as_ref().unwrap();
+        match &root.tree {
+            Node::Branch { left, right } => {
+                let (a, b) = (left.bounding_box, right.bounding_box);
+                assert!(a.max.x < 1e3 || b.max.x < 1e3, "outlier not isolated");
+            }
+            Node::Leaf(_) => panic!("expected root to be split"),
+        }
+        assert!(depth(root) < 12);
+    }
+
+    #[test]
+    fn flattened_tree_keeps_unbounded_objects() {
+        let mut world = row_of_spheres(16);
+        world.push(Box::new(Ground::default()));
+
+        let linear = LinearBvh::new(world, 0.0, 0.0).unwrap();
+        assert!(linear.bounding_box(0.0, 0.0).is_none());
+
+        let onto_sphere = Ray::new(Point3::new(3.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let onto_ground = Ray::new(Point3::new(1.5, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert_eq!(linear.hit(&onto_sphere, (0.0, f64::MAX)).unwrap().t, 4.0);
+        assert_eq!(linear.hit(&onto_ground, (0.0, f64::MAX)).unwrap().t, 5.0);
+        assert!(linear.occluded(&onto_ground, (0.0, f64::MAX)));
+
+        let only_ground = LinearBvh::new(vec![Box::new(Ground::default())], 0.0, 0.0).unwrap();
+        assert!(only_ground.hit(&onto_ground, (0.0, f64::MAX)).is_some());
+        assert!(only_ground.hit(&onto_sphere, (0.0, 3.0)).is_none());
+    }
+
+    #[test]
+    fn keeps_unbounded_objects_beside_tree() {
+        let mut world = row_of_spheres(16);
+        world.push(Box::new(Ground::default()));
+
+        let bvh = Bvh::new(world, 0.0, 0.0).unwrap();
+        assert_eq!(bvh.unbounded.len(), 1);
+        assert!(bvh.bounding_box(0.0, 0.0).is_none());
+
+        let onto_sphere = Ray::new(Point3::new(3.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let onto_ground = Ray::new(Point3::new(1.5, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert_eq!(bvh.hit(&onto_sphere, (0.0, f64::MAX)).unwrap().t, 4.0);
+        assert_eq!(bvh.hit(&onto_ground, (0.0, f64::MAX)).unwrap().t, 5.0);
+
+        let only_ground = Bvh::new(vec![Box::new(Ground::default())], 0.0, 0.0).unwrap();
+        assert!(only_ground.hit(&onto_ground, (0.0, f64::MAX)).is_some());
     }
 }
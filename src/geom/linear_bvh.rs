@@ -0,0 +1,318 @@
+use super::bvh::{Bvh, BvhNode, BvhOptions, Node};
+use super::{HitRecord, Hittable};
+use crate::aabb::Aabb;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// Trees up to this depth are traversed with a stack allocated on the call stack.
+const MAX_STACK_DEPTH: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum LinearNodeKind {
+    /// Objects `first..first + count` of `LinearBvh::objects`.
+    Leaf { first: u32, count: u32 },
+    /// The first child immediately follows its parent; `second_child` is the index of the other.
+    /// Children are ordered so that the first lies nearer the negative end of `axis`.
+    Branch { second_child: u32, axis: u8 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct LinearNode {
+    bounding_box: Aabb,
+    kind: LinearNodeKind,
+}
+
+/// A `Bvh` flattened into a contiguous, depth-first array of nodes.
+///
+/// Traversal is iterative and visits the child nearer to the ray origin first, which lets the
+/// farther child be culled more often once a hit has been found. As with `Bvh`, unbounded objects
+/// are kept aside and tested on every ray.
+#[derive(Debug)]
+pub struct LinearBvh {
+    nodes: Vec<LinearNode>,
+    objects: Vec<Box<dyn Hittable>>,
+    unbounded: Vec<Box<dyn Hittable>>,
+    depth: usize,
+}
+
+impl LinearBvh {
+    pub fn new(world: Vec<Box<dyn Hittable>>, time0: f64, time1: f64) -> anyhow::Result<Self> {
+        Bvh::new(world, time0, time1).map(LinearBvh::from)
+    }
+
+    pub fn with_options(
+        world: Vec<Box<dyn Hittable>>,
+        time0: f64,
+        time1: f64,
+        options: BvhOptions,
+    ) -> anyhow::Result<Self> {
+        Bvh::with_options(world, time0, time1, options).map(LinearBvh::from)
+    }
+
+    fn flatten(&mut self, node: BvhNode, depth: usize) -> usize {
+        let index = self.nodes.len();
+        self.depth = self.depth.max(depth);
+        let BvhNode { tree, bounding_box } = node;
+
+        match tree {
+            Node::Leaf(objects) => {
+                let kind = LinearNodeKind::Leaf {
+                    first: self.objects.len() as u32,
+                    count: objects.len() as u32,
+                };
+                self.objects.extend(objects);
+                self.nodes.push(LinearNode { bounding_box, kind });
+            }
+            Node::Branch { left, right } => {
+                let offset = right.bounding_box.centroid() - left.bounding_box.centroid();
+                let axis = (0..3)
+                    .max_by(|&a, &b| offset[a].abs().partial_cmp(&offset[b].abs()).unwrap())
+                    .unwrap_or(0);
+                let (first, second) = if offset[axis] < 0.0 {
+                    (right, left)
+                } else {
+                    (left, right)
+                };
+
+                let placeholder = LinearNodeKind::Branch {
+                    second_child: 0,
+                    axis: axis as u8,
+                };
+                self.nodes.push(LinearNode {
+                    bounding_box,
+                    kind: placeholder,
+                });
+
+                self.flatten(*first, depth + 1);
+                let second_child = self.flatten(*second, depth + 1) as u32;
+                self.nodes[index].kind = LinearNodeKind::Branch {
+                    second_child,
+                    axis: axis as u8,
+                };
+            }
+        }
+
+        index
+    }
+}
+
+impl From<Bvh> for LinearBvh {
+    fn from(bvh: Bvh) -> Self {
+        let mut linear = LinearBvh {
+            nodes: Vec::new(),
+            objects: Vec::new(),
+            unbounded: bvh.unbounded,
+            depth: 0,
+        };
+        if let Some(root) = bvh.root {
+            linear.flatten(root, 1);
+        }
+        linear
+    }
+}
+
+impl LinearBvh {
+    /// Finds the closest hit, using `stack` to hold the far children still left to visit.
+    fn traverse(
+        &self,
+        ray: &Ray,
+        (t_min, t_max): (f64, f64),
+        stack: &mut [usize],
+    ) -> Option<HitRecord<'_>> {
+        let inv_direction = Vec3::new(
+            1.0 / ray.direction.x,
+            1.0 / ray.direction.y,
+            1.0 / ray.direction.z,
+        );
+        let direction_is_negative = [
+            inv_direction.x < 0.0,
+            inv_direction.y < 0.0,
+            inv_direction.z < 0.0,
+        ];
+
+        let mut closest_so_far: Option<HitRecord> = None;
+        let mut t_max = t_max;
+
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let mut stack_len = 0;
+        let mut current = 0;
+
+        loop {
+            let node = &self.nodes[current];
+            if node
+                .bounding_box
+                .hit_precomputed(ray.origin, inv_direction, (t_min, t_max))
+            {
+                match node.kind {
+                    LinearNodeKind::Leaf { first, count } => {
+                        let (first, count) = (first as usize, count as usize);
+                        for object in &self.objects[first..first + count] {
+                            if let Some(record) = object.hit(ray, (t_min, t_max)) {
+                                t_max = record.t;
+                                closest_so_far = Some(record);
+                            }
+                        }
+                    }
+                    LinearNodeKind::Branch { second_child, axis } => {
+                        let (near, far) = if direction_is_negative[axis as usize] {
+                            (second_child as usize, current + 1)
+                        } else {
+                            (current + 1, second_child as usize)
+                        };
+
+                        stack[stack_len] = far;
+                        stack_len += 1;
+                        current = near;
+                        continue;
+                    }
+                }
+            }
+
+            if stack_len == 0 {
+                break;
+            }
+            stack_len -= 1;
+            current = stack[stack_len];
+        }
+
+        closest_so_far
+    }
+
+    /// Like `traverse()`, but returns as soon as any object is found within `t_range`.
+    fn traverse_occluded(&self, ray: &Ray, t_range: (f64, f64), stack: &mut [usize]) -> bool {
+        if self.nodes.is_empty() {
+            return false;
+        }
+
+        let inv_direction = Vec3::new(
+            1.0 / ray.direction.x,
+            1.0 / ray.direction.y,
+            1.0 / ray.direction.z,
+        );
+
+        let mut stack_len = 0;
+        let mut current = 0;
+
+        loop {
+            let node = &self.nodes[current];
+            if node
+                .bounding_box
+                .hit_precomputed(ray.origin, inv_direction, t_range)
+            {
+                match node.kind {
+                    LinearNodeKind::Leaf { first, count } => {
+                        let (first, count) = (first as usize, count as usize);
+                        let objects = &self.objects[first..first + count];
+                        if objects.iter().any(|object| object.occluded(ray, t_range)) {
+                            return true;
+                        }
+                    }
+                    LinearNodeKind::Branch { second_child, .. } => {
+                        stack[stack_len] = second_child as usize;
+                        stack_len += 1;
+                        current += 1;
+                        continue;
+                    }
+                }
+            }
+
+            if stack_len == 0 {
+                return false;
+            }
+            stack_len -= 1;
+            current = stack[stack_len];
+        }
+    }
+}
+
+impl Hittable for LinearBvh {
+    fn hit(&self, ray: &Ray, (t_min, t_max): (f64, f64)) -> Option<HitRecord<'_>> {
+        let hit_unbounded = self.unbounded.hit(ray, (t_min, t_max));
+        let t_range = (
+            t_min,
+            hit_unbounded.as_ref().map(|hit| hit.t).unwrap_or(t_max),
+        );
+
+        let hit_tree = if self.depth <= MAX_STACK_DEPTH {
+            self.traverse(ray, t_range, &mut [0; MAX_STACK_DEPTH])
+        } else {
+            self.traverse(ray, t_range, &mut vec![0; self.depth])
+        };
+
+        hit_tree.or(hit_unbounded)
+    }
+
+    fn occluded(&self, ray: &Ray, t_range: (f64, f64)) -> bool {
+        if self.unbounded.occluded(ray, t_range) {
+            return true;
+        }
+
+        if self.depth <= MAX_STACK_DEPTH {
+            self.traverse_occluded(ray, t_range, &mut [0; MAX_STACK_DEPTH])
+        } else {
+            self.traverse_occluded(ray, t_range, &mut vec![0; self.depth])
+        }
+    }
+
+    fn bounding_box(&self, _: f64, _: f64) -> Option<Aabb> {
+        if self.unbounded.is_empty() {
+            self.nodes.first().map(|root| root.bounding_box)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::geom::{Sphere, SplitMethod};
+    use crate::mat::Lambertian;
+    use crate::vec3::Point3;
+
+    fn random_spheres(rng: &mut impl Rng, count: usize) -> Vec<Box<dyn Hittable>> {
+        (0..count)
+            .map(|_| {
+                let center = Point3::new(
+                    rng.gen_range(-50.0, 50.0),
+                    rng.gen_range(-50.0, 50.0),
+                    rng.gen_range(-50.0, 50.0),
+                );
+                let radius = rng.gen_range(0.1, 2.0);
+                Box::new(Sphere::new(center, radius, Lambertian::default())) as Box<dyn Hittable>
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matches_tree_traversal() {
+        let world = |seed| random_spheres(&mut rand::rngs::StdRng::seed_from_u64(seed), 500);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(11);
+
+        for &method in &[SplitMethod::Sah, SplitMethod::Median] {
+            let options = BvhOptions::default().with_split_method(method);
+            let tree = Bvh::with_options(world(7), 0.0, 0.0, options).unwrap();
+            let linear = LinearBvh::with_options(world(7), 0.0, 0.0, options).unwrap();
+            assert_eq!(linear.objects.len(), 500);
+
+            for _ in 0..1000 {
+                let direction = Vec3::random_in_range(&mut rng, -1.0, 1.0);
+                let ray = Ray::new(Point3::zeros(), direction);
+
+                let expected = tree.hit(&ray, (0.001, f64::MAX)).map(|hit| hit.t);
+                let actual = linear.hit(&ray, (0.001, f64::MAX)).map(|hit| hit.t);
+                assert_eq!(expected, actual);
+
+                let segment = (0.001, 20.0);
+                let blocked = tree.hit(&ray, segment).is_some();
+                assert_eq!(tree.occluded(&ray, segment), blocked);
+                assert_eq!(linear.occluded(&ray, segment), blocked);
+            }
+        }
+    }
+}
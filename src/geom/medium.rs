@@ -0,0 +1,127 @@
+use super::{HitRecord, Hittable};
+use crate::aabb::Aabb;
+use crate::mat::{Isotropic, Texture};
+use crate::random;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// Offset used when searching for the far side of the boundary, to avoid re-hitting the near side.
+const EXIT_EPSILON: f64 = 1e-4;
+
+/// A volume of uniform density, such as fog or smoke, filling a closed convex boundary.
+///
+/// Rays passing through the volume scatter at an exponentially distributed distance according
+/// to `density`, after which the `Isotropic` phase function picks a new direction.
+#[derive(Debug)]
+pub struct ConstantMedium<T: Texture> {
+    pub boundary: Box<dyn Hittable>,
+    pub phase_function: Isotropic<T>,
+    neg_inv_density: f64,
+}
+
+impl<T: Texture> ConstantMedium<T> {
+    pub fn new(boundary: Box<dyn Hittable>, density: f64, albedo: T) -> Self {
+        ConstantMedium {
+            boundary,
+            phase_function: Isotropic::new(albedo),
+            neg_inv_density: -1.0 / density,
+        }
+    }
+
+    pub fn density(&self) -> f64 {
+        -1.0 / self.neg_inv_density
+    }
+}
+
+impl<T: Texture> Hittable for ConstantMedium<T> {
+    fn hit(&self, ray: &Ray, (t_min, t_max): (f64, f64)) -> Option<HitRecord<'_>> {
+        // Find both boundary crossings along the whole line, so that rays starting inside the
+        // volume still see the entry point behind them.
+        let entry = self
+            .boundary
+            .hit(ray, (f64::NEG_INFINITY, f64::INFINITY))?
+            .t;
+        let exit = self
+            .boundary
+            .hit(ray, (entry + EXIT_EPSILON, f64::INFINITY))?
+            .t;
+
+        let t_enter = entry.max(t_min);
+        let t_exit = exit.min(t_max);
+        if t_enter >= t_exit {
+            return None;
+        }
+
+        let ray_length = ray.direction.len();
+        let distance_inside = (t_exit - t_enter) * ray_length;
+        // Hashing the ray keeps the sampled distance reproducible without a generator at hand.
+        let sample = random::hash_to_unit(&[
+            ray.origin.x,
+            ray.origin.y,
+            ray.origin.z,
+            ray.direction.x,
+            ray.direction.y,
+            ray.direction.z,
+            ray.time,
+            t_enter,
+        ]);
+        let hit_distance = self.neg_inv_density * (1.0 - sample).ln();
+        if hit_distance > distance_inside {
+            return None;
+        }
+
+        let t = t_enter + hit_distance / ray_length;
+
+        // The normal and facing are meaningless inside a volume, so arbitrary values are used.
+        Some(HitRecord::new(
+            ray.point_at(t),
+            Vec3::new(1.0, 0.0, 0.0),
+            &self.phase_function,
+            0.0,
+            0.0,
+            t,
+            true,
+        ))
+    }
+
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<Aabb> {
+        self.boundary.bounding_box(time0, time1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geom::Sphere;
+    use crate::mat::Lambertian;
+    use crate::vec3::{Color, Point3};
+
+    fn fog(density: f64) -> ConstantMedium<Color> {
+        let boundary = Sphere::new(Point3::zeros(), 1.0, Lambertian::default());
+        ConstantMedium::new(Box::new(boundary), density, Color::ones())
+    }
+
+    #[test]
+    fn dense_medium_scatters_at_entry() {
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let medium = fog(1e12);
+        let hit = medium.hit(&ray, (0.001, f64::MAX)).expect("ray missed");
+        assert!((hit.t - 4.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn ray_starting_inside_is_clipped_to_t_min() {
+        let ray = Ray::new(Point3::zeros(), Vec3::new(0.0, 0.0, -1.0));
+        let medium = fog(1e12);
+        let hit = medium.hit(&ray, (0.5, f64::MAX)).expect("ray missed");
+        assert!((hit.t - 0.5).abs() < 1e-6);
+
+        assert!(medium.hit(&ray, (1.5, f64::MAX)).is_none());
+    }
+
+    #[test]
+    fn empty_medium_is_transparent() {
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(fog(0.0).hit(&ray, (0.001, f64::MAX)).is_none());
+    }
+}
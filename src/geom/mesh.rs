@@ -0,0 +1,140 @@
+use std::sync::Arc;
+
+use super::triangle::{hit_triangle, triangle_bounding_box};
+use super::{HitRecord, Hittable};
+use crate::aabb::{self, Aabb};
+use crate::mat::Material;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+
+/// A single triangle of a `TriangleMesh`, expressed as indices into the shared vertex arrays.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Face {
+    pub positions: [usize; 3],
+    pub normals: Option<[usize; 3]>,
+    pub uvs: Option<[usize; 3]>,
+    pub material: usize,
+}
+
+/// A triangle mesh whose faces share a common pool of vertex attributes and materials.
+///
+/// The mesh can be hit directly, which tests every face in turn, but large meshes should be
+/// split with `into_triangles()` and placed in a `Bvh` instead.
+#[derive(Debug)]
+pub struct TriangleMesh {
+    pub positions: Vec<Point3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<(f64, f64)>,
+    pub faces: Vec<Face>,
+    pub materials: Vec<Box<dyn Material>>,
+}
+
+impl TriangleMesh {
+    pub fn new(
+        positions: Vec<Point3>,
+        faces: Vec<Face>,
+        materials: Vec<Box<dyn Material>>,
+    ) -> Self {
+        TriangleMesh {
+            positions,
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            faces,
+            materials,
+        }
+    }
+
+    pub fn with_normals(mut self, normals: Vec<Vec3>) -> Self {
+        self.normals = normals;
+        self
+    }
+
+    pub fn with_uvs(mut self, uvs: Vec<(f64, f64)>) -> Self {
+        self.uvs = uvs;
+        self
+    }
+
+    /// Splits the mesh into one hittable per face, all sharing this mesh's vertex data.
+    pub fn into_triangles(self) -> Vec<Box<dyn Hittable>> {
+        let mesh = Arc::new(self);
+        (0..mesh.faces.len())
+            .map(|face| {
+                let triangle = MeshTriangle {
+                    mesh: mesh.clone(),
+                    face,
+                };
+                Box::new(triangle) as Box<dyn Hittable>
+            })
+            .collect()
+    }
+
+    fn hit_face(&self, face: &Face, ray: &Ray, t_range: (f64, f64)) -> Option<HitRecord<'_>> {
+        let [a, b, c] = face.positions;
+        let vertices = [self.positions[a], self.positions[b], self.positions[c]];
+        let normals = face
+            .normals
+            .map(|[a, b, c]| [self.normals[a], self.normals[b], self.normals[c]]);
+        let uvs = match face.uvs {
+            Some([a, b, c]) => [self.uvs[a], self.uvs[b], self.uvs[c]],
+            None => [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)],
+        };
+
+        let material = &*self.materials[face.material];
+        hit_triangle(&vertices, normals.as_ref(), &uvs, material, ray, t_range)
+    }
+
+    fn face_bounding_box(&self, face: &Face) -> Aabb {
+        let [a, b, c] = face.positions;
+        triangle_bounding_box(&[self.positions[a], self.positions[b], self.positions[c]])
+    }
+}
+
+impl Hittable for TriangleMesh {
+    fn hit(&self, ray: &Ray, (t_min, t_max): (f64, f64)) -> Option<HitRecord<'_>> {
+        let mut closest_so_far: Option<HitRecord> = None;
+        let mut t_max = t_max;
+
+        for face in &self.faces {
+            if let Some(record) = self.hit_face(face, ray, (t_min, t_max)) {
+                t_max = record.t;
+                closest_so_far = Some(record);
+            }
+        }
+
+        closest_so_far
+    }
+
+    fn occluded(&self, ray: &Ray, t_range: (f64, f64)) -> bool {
+        self.faces
+            .iter()
+            .any(|face| self.hit_face(face, ray, t_range).is_some())
+    }
+
+    fn bounding_box(&self, _: f64, _: f64) -> Option<Aabb> {
+        self.faces
+            .iter()
+            .map(|face| self.face_bounding_box(face))
+            .fold(None, |acc, b| match acc {
+                Some(acc) => Some(aabb::surrounding_box(acc, b)),
+                None => Some(b),
+            })
+    }
+}
+
+/// One face of a shared `TriangleMesh`, as produced by `TriangleMesh::into_triangles()`.
+#[derive(Debug)]
+struct MeshTriangle {
+    mesh: Arc<TriangleMesh>,
+    face: usize,
+}
+
+impl Hittable for MeshTriangle {
+    fn hit(&self, ray: &Ray, t_range: (f64, f64)) -> Option<HitRecord<'_>> {
+        self.mesh
+            .hit_face(&self.mesh.faces[self.face], ray, t_range)
+    }
+
+    fn bounding_box(&self, _: f64, _: f64) -> Option<Aabb> {
+        Some(self.mesh.face_bounding_box(&self.mesh.faces[self.face]))
+    }
+}
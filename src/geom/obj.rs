@@ -0,0 +1,340 @@
+//! Loader for Wavefront OBJ meshes and their accompanying MTL material libraries.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use anyhow::{anyhow, format_err, Context};
+
+use super::mesh::{Face, TriangleMesh};
+use crate::mat::{Dielectric, Lambertian, Material, Metallic};
+use crate::vec3::{Color, Point3, Vec3};
+
+const DEFAULT_MATERIAL: usize = 0;
+
+impl TriangleMesh {
+    /// Loads a mesh from an OBJ file, resolving any `mtllib` statements relative to its directory.
+    pub fn from_obj<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let file =
+            File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+        TriangleMesh::from_obj_reader(BufReader::new(file), base_dir)
+            .with_context(|| format!("Failed to load {}", path.display()))
+    }
+
+    /// Parses an OBJ mesh from `reader`, resolving any `mtllib` statements relative to `base_dir`.
+    ///
+    /// Polygonal faces are fan-triangulated. Faces preceding the first `usemtl` statement are
+    /// given a default grey `Lambertian` material.
+    pub fn from_obj_reader<R: BufRead>(reader: R, base_dir: &Path) -> anyhow::Result<Self> {
+        let mut positions = Vec::new();
+        let mut normals = Vec::new();
+        let mut uvs = Vec::new();
+        let mut faces = Vec::new();
+        let mut materials: Vec<Box<dyn Material>> = vec![Box::new(Lambertian::default())];
+        let mut material_names = HashMap::new();
+        let mut current_material = DEFAULT_MATERIAL;
+
+        for (index, line) in reader.lines().enumerate() {
+            let line_no = index + 1;
+            let line = line?;
+            let mut tokens = strip_comment(&line).split_whitespace();
+            let keyword = match tokens.next() {
+                Some(keyword) => keyword,
+                None => continue,
+            };
+
+            let result = match keyword {
+                "v" => parse_vec3(tokens).map(|v| positions.push(v)),
+                "vn" => parse_vec3(tokens).map(|v| normals.push(v)),
+                "vt" => parse_uv(tokens).map(|uv| uvs.push(uv)),
+                "f" => {
+                    let counts = (positions.len(), uvs.len(), normals.len());
+                    parse_face(tokens, counts, current_material).map(|f| faces.extend(f))
+                }
+                "usemtl" => match tokens.next() {
+                    Some(name) => material_names
+                        .get(name)
+                        .map(|&index| current_material = index)
+                        .ok_or_else(|| format_err!("Unknown material `{}`", name)),
+                    None => Err(format_err!("Expected material name")),
+                },
+                "mtllib" => tokens.try_for_each(|file| {
+                    let library = load_mtl(&base_dir.join(file))?;
+                    for (name, material) in library {
+                        material_names.insert(name, materials.len());
+                        materials.push(material);
+                    }
+                    Ok(())
+                }),
+                _ => Ok(()),
+            };
+
+            result.map_err(|e| format_err!("Line {}: {}", line_no, e))?;
+        }
+
+        if faces.is_empty() {
+            return Err(anyhow!("Mesh contains no faces"));
+        }
+
+        Ok(TriangleMesh::new(positions, faces, materials)
+            .with_normals(normals)
+            .with_uvs(uvs))
+    }
+}
+
+/// Material parameters accumulated from a `newmtl` block.
+#[derive(Debug, Default)]
+struct MtlDesc {
+    diffuse: Option<Color>,
+    specular: Option<Color>,
+    specular_exponent: Option<f64>,
+    refraction_index: Option<f64>,
+    dissolve: Option<f64>,
+    illum: Option<u32>,
+}
+
+impl MtlDesc {
+    /// Maps the MTL parameters onto the closest of the available material models.
+    fn into_material(self) -> Box<dyn Material> {
+        let is_black = |c: Option<Color>| c.map(|c| c == Color::zeros()).unwrap_or(true);
+        let is_transparent =
+            self.dissolve.map(|d| d < 1.0).unwrap_or(false) || matches!(self.illum, Some(4..=7));
+        let is_mirror =
+            self.illum == Some(3) || (is_black(self.diffuse) && !is_black(self.specular));
+
+        if is_transparent {
+            Box::new(Dielectric::new(self.refraction_index.unwrap_or(1.5)))
+        } else if is_mirror {
+            let albedo = self.specular.unwrap_or_else(Color::ones);
+            let fuzz = 1.0 - (self.specular_exponent.unwrap_or(1000.0) / 1000.0).min(1.0);
+            Box::new(Metallic::new(albedo, fuzz))
+        } else {
+            let albedo = self.diffuse.unwrap_or_else(|| Color::new(0.5, 0.5, 0.5));
+            Box::new(Lambertian::new(albedo))
+        }
+    }
+}
+
+fn load_mtl(path: &Path) -> anyhow::Result<Vec<(String, Box<dyn Material>)>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    parse_mtl(BufReader::new(file)).with_context(|| format!("Failed to load {}", path.display()))
+}
+
+fn parse_mtl<R: BufRead>(reader: R) -> anyhow::Result<Vec<(String, Box<dyn Material>)>> {
+    let mut library = Vec::new();
+    let mut current: Option<(String, MtlDesc)> = None;
+
+    for (index, line) in reader.lines().enumerate() {
+        let line_no = index + 1;
+        let line = line?;
+        let mut tokens = strip_comment(&line).split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+
+        if keyword == "newmtl" {
+            let name = tokens
+                .next()
+                .ok_or_else(|| format_err!("Line {}: Expected material name", line_no))?;
+            if let Some((name, desc)) = current.replace((name.to_owned(), MtlDesc::default())) {
+                library.push((name, desc.into_material()));
+            }
+            continue;
+        }
+
+        let desc = match current.as_mut() {
+            Some((_, desc)) => desc,
+            None => continue,
+        };
+
+        let result = match keyword {
+            "Kd" => parse_vec3(tokens).map(|c| desc.diffuse = Some(c)),
+            "Ks" => parse_vec3(tokens).map(|c| desc.specular = Some(c)),
+            "Ns" => parse_scalar(tokens).map(|x| desc.specular_exponent = Some(x)),
+            "Ni" => parse_scalar(tokens).map(|x| desc.refraction_index = Some(x)),
+            "d" => parse_scalar(tokens).map(|x| desc.dissolve = Some(x)),
+            "Tr" => parse_scalar(tokens).map(|x| desc.dissolve = Some(1.0 - x)),
+            "illum" => parse_scalar(tokens).map(|x| desc.illum = Some(x as u32)),
+            _ => Ok(()),
+        };
+
+        result.map_err(|e| format_err!("Line {}: {}", line_no, e))?;
+    }
+
+    if let Some((name, desc)) = current {
+        library.push((name, desc.into_material()));
+    }
+
+    Ok(library)
+}
+
+fn strip_comment(line: &str) -> &str {
+    match line.find('#') {
+        Some(index) => &line[..index],
+        None => line,
+    }
+}
+
+fn parse_f64(token: Option<&str>) -> anyhow::Result<f64> {
+    let token = token.ok_or_else(|| format_err!("Expected a number"))?;
+    token
+        .parse()
+        .map_err(|_| format_err!("Invalid number `{}`", token))
+}
+
+fn parse_scalar<'a, I: Iterator<Item = &'a str>>(mut tokens: I) -> anyhow::Result<f64> {
+    parse_f64(tokens.next())
+}
+
+fn parse_vec3<'a, I: Iterator<Item = &'a str>>(mut tokens: I) -> anyhow::Result<Vec3> {
+    let x = parse_f64(tokens.next())?;
+    let y = parse_f64(tokens.next())?;
+    let z = parse_f64(tokens.next())?;
+    Ok(Point3::new(x, y, z))
+}
+
+fn parse_uv<'a, I: Iterator<Item = &'a str>>(mut tokens: I) -> anyhow::Result<(f64, f64)> {
+    let u = parse_f64(tokens.next())?;
+    let v = match tokens.next() {
+        Some(token) => parse_f64(Some(token))?,
+        None => 0.0,
+    };
+    Ok((u, v))
+}
+
+/// Resolves a one-based (or negative, relative) OBJ index into a zero-based one.
+fn resolve_index(token: &str, count: usize) -> anyhow::Result<usize> {
+    let index: i64 = token
+        .parse()
+        .map_err(|_| format_err!("Invalid index `{}`", token))?;
+
+    let resolved = if index > 0 {
+        index - 1
+    } else {
+        count as i64 + index
+    };
+
+    if index == 0 || resolved < 0 || resolved >= count as i64 {
+        Err(format_err!("Index {} is out of range", index))
+    } else {
+        Ok(resolved as usize)
+    }
+}
+
+/// Parses a polygonal `f` statement and fan-triangulates it into faces.
+fn parse_face<'a, I: Iterator<Item = &'a str>>(
+    tokens: I,
+    (num_positions, num_uvs, num_normals): (usize, usize, usize),
+    material: usize,
+) -> anyhow::Result<Vec<Face>> {
+    let mut corners = Vec::new();
+
+    for vertex in tokens {
+        let mut parts = vertex.split('/');
+        let position = resolve_index(parts.next().unwrap_or(""), num_positions)?;
+        let uv = match parts.next() {
+            Some("") | None => None,
+            Some(token) => Some(resolve_index(token, num_uvs)?),
+        };
+        let normal = match parts.next() {
+            Some("") | None => None,
+            Some(token) => Some(resolve_index(token, num_normals)?),
+        };
+        corners.push((position, uv, normal));
+    }
+
+    if corners.len() < 3 {
+        return Err(format_err!("Face has fewer than three vertices"));
+    }
+
+    let faces = (1..corners.len() - 1)
+        .map(|i| {
+            let (a, b, c) = (corners[0], corners[i], corners[i + 1]);
+            Face {
+                positions: [a.0, b.0, c.0],
+                uvs: a.1.and_then(|a| Some([a, b.1?, c.1?])),
+                normals: a.2.and_then(|a| Some([a, b.2?, c.2?])),
+                material,
+            }
+        })
+        .collect();
+
+    Ok(faces)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geom::Hittable;
+    use crate::ray::Ray;
+
+    const QUAD: &str = "
+        # A unit quad in the XY plane
+        v 0 0 0
+        v 1 0 0
+        v 1 1 0
+        v 0 1 0
+        vt 0 0
+        vt 1 0
+        vt 1 1
+        vt 0 1
+        vn 0 0 1
+        f 1/1/1 2/2/1 3/3/1 -1/-1/-1
+    ";
+
+    #[test]
+    fn triangulates_polygons() {
+        let mesh = TriangleMesh::from_obj_reader(QUAD.as_bytes(), Path::new("")).unwrap();
+        assert_eq!(mesh.positions.len(), 4);
+        assert_eq!(mesh.faces.len(), 2);
+        assert_eq!(mesh.faces[1].positions, [0, 2, 3]);
+        assert_eq!(mesh.faces[1].uvs, Some([0, 2, 3]));
+        assert_eq!(mesh.faces[1].normals, Some([0, 0, 0]));
+
+        let ray = Ray::new(Point3::new(0.25, 0.75, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = mesh.hit(&ray, (0.0, f64::MAX)).expect("ray missed");
+        assert!((hit.texture_u - 0.25).abs() < 1e-9);
+        assert!((hit.texture_v - 0.75).abs() < 1e-9);
+    }
+
+    #[test]
+    fn reports_line_of_bad_index() {
+        let source = "v 0 0 0\nv 1 0 0\nf 1 2 3\n";
+        let error = TriangleMesh::from_obj_reader(source.as_bytes(), Path::new("")).unwrap_err();
+        assert_eq!(error.to_string(), "Line 3: Index 3 is out of range");
+    }
+
+    #[test]
+    fn rejects_unknown_material() {
+        let source = "v 0 0 0\nusemtl missing\n";
+        let error = TriangleMesh::from_obj_reader(source.as_bytes(), Path::new("")).unwrap_err();
+        assert_eq!(error.to_string(), "Line 2: Unknown material `missing`");
+    }
+
+    #[test]
+    fn maps_mtl_onto_materials() {
+        let source = "
+            newmtl matte
+            Kd 0.8 0.1 0.1
+            newmtl mirror
+            Kd 0 0 0
+            Ks 0.9 0.9 0.9
+            Ns 1000
+            newmtl glass
+            Ni 1.5
+            d 0.0
+        ";
+        let library = parse_mtl(source.as_bytes()).unwrap();
+        let names: Vec<_> = library.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["matte", "mirror", "glass"]);
+
+        let debug: Vec<_> = library.iter().map(|(_, m)| format!("{:?}", m)).collect();
+        assert!(debug[0].starts_with("Lambertian"));
+        assert!(debug[1].starts_with("Metallic"));
+        assert!(debug[2].starts_with("Dielectric"));
+    }
+}
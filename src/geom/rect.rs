@@ -0,0 +1,377 @@
+use rand::{Rng, RngCore};
+
+use super::{HitRecord, Hittable, SAMPLING_T_RANGE};
+use crate::aabb::Aabb;
+use crate::mat::Material;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+
+const BOX_PADDING: f64 = 1e-4;
+
+/// A rectangle in the plane `z = k`, facing towards `+z`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct XyRect<M: Material> {
+    pub x: (f64, f64),
+    pub y: (f64, f64),
+    pub k: f64,
+    pub material: M,
+}
+
+impl<M: Material> XyRect<M> {
+    pub fn new(x: (f64, f64), y: (f64, f64), k: f64, material: M) -> Self {
+        XyRect { x, y, k, material }
+    }
+}
+
+impl<M: Material> Hittable for XyRect<M> {
+    fn hit(&self, ray: &Ray, t_range: (f64, f64)) -> Option<HitRecord<'_>> {
+        hit_rect(
+            ray,
+            t_range,
+            (0, 1, 2),
+            self.x,
+            self.y,
+            self.k,
+            &self.material,
+        )
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let area = (self.x.1 - self.x.0) * (self.y.1 - self.y.0);
+        rect_pdf_value(self, area, origin, direction)
+    }
+
+    fn sample_direction(&self, origin: Point3, rng: &mut dyn RngCore) -> Option<Vec3> {
+        Some(sample_rect((0, 1, 2), self.x, self.y, self.k, rng) - origin)
+    }
+
+    fn bounding_box(&self, _: f64, _: f64) -> Option<Aabb> {
+        let min = Point3::new(self.x.0, self.y.0, self.k);
+        let max = Point3::new(self.x.1, self.y.1, self.k);
+        Some(Aabb::new(min, max).padded(BOX_PADDING))
+    }
+}
+
+/// A rectangle in the plane `y = k`, facing towards `+y`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct XzRect<M: Material> {
+    pub x: (f64, f64),
+    pub z: (f64, f64),
+    pub k: f64,
+    pub material: M,
+}
+
+impl<M: Material> XzRect<M> {
+    pub fn new(x: (f64, f64), z: (f64, f64), k: f64, material: M) -> Self {
+        XzRect { x, z, k, material }
+    }
+}
+
+impl<M: Material> Hittable for XzRect<M> {
+    fn hit(&self, ray: &Ray, t_range: (f64, f64)) -> Option<HitRecord<'_>> {
+        hit_rect(
+            ray,
+            t_range,
+            (0, 2, 1),
+            self.x,
+            self.z,
+            self.k,
+            &self.material,
+        )
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let area = (self.x.1 - self.x.0) * (self.z.1 - self.z.0);
+        rect_pdf_value(self, area, origin, direction)
+    }
+
+    fn sample_direction(&self, origin: Point3, rng: &mut dyn RngCore) -> Option<Vec3> {
+        Some(sample_rect((0, 2, 1), self.x, self.z, self.k, rng) - origin)
+    }
+
+    fn bounding_box(&self, _: f64, _: f64) -> Option<Aabb> {
+        let min = Point3::new(self.x.0, self.k, self.z.0);
+        let max = Point3::new(self.x.1, self.k, self.z.1);
+        Some(Aabb::new(min, max).padded(BOX_PADDING))
+    }
+}
+
+/// A rectangle in the plane `x = k`, facing towards `+x`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct YzRect<M: Material> {
+    pub y: (f64, f64),
+    pub z: (f64, f64),
+    pub k: f64,
+    pub material: M,
+}
+
+impl<M: Material> YzRect<M> {
+    pub fn new(y: (f64, f64), z: (f64, f64), k: f64, material: M) -> Self {
+        YzRect { y, z, k, material }
+    }
+}
+
+impl<M: Material> Hittable for YzRect<M> {
+    fn hit(&self, ray: &Ray, t_range: (f64, f64)) -> Option<HitRecord<'_>> {
+        hit_rect(
+            ray,
+            t_range,
+            (1, 2, 0),
+            self.y,
+            self.z,
+            self.k,
+            &self.material,
+        )
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let area = (self.y.1 - self.y.0) * (self.z.1 - self.z.0);
+        rect_pdf_value(self, area, origin, direction)
+    }
+
+    fn sample_direction(&self, origin: Point3, rng: &mut dyn RngCore) -> Option<Vec3> {
+        Some(sample_rect((1, 2, 0), self.y, self.z, self.k, rng) - origin)
+    }
+
+    fn bounding_box(&self, _: f64, _: f64) -> Option<Aabb> {
+        let min = Point3::new(self.k, self.y.0, self.z.0);
+        let max = Point3::new(self.k, self.y.1, self.z.1);
+        Some(Aabb::new(min, max).padded(BOX_PADDING))
+    }
+}
+
+/// Reverses which side of the wrapped object is considered its front face.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FlipFace<H: Hittable> {
+    pub inner: H,
+}
+
+impl<H: Hittable> FlipFace<H> {
+    pub fn new(inner: H) -> Self {
+        FlipFace { inner }
+    }
+}
+
+impl<H: Hittable> Hittable for FlipFace<H> {
+    fn hit(&self, ray: &Ray, t_range: (f64, f64)) -> Option<HitRecord<'_>> {
+        let mut record = self.inner.hit(ray, t_range)?;
+        record.is_front_face = !record.is_front_face;
+        Some(record)
+    }
+
+    fn occluded(&self, ray: &Ray, t_range: (f64, f64)) -> bool {
+        self.inner.occluded(ray, t_range)
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        self.inner.pdf_value(origin, direction)
+    }
+
+    fn sample_direction(&self, origin: Point3, rng: &mut dyn RngCore) -> Option<Vec3> {
+        self.inner.sample_direction(origin, rng)
+    }
+
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<Aabb> {
+        self.inner.bounding_box(time0, time1)
+    }
+}
+
+/// An axis-aligned box built from six rectangles with outward-facing normals.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cuboid<M: Material> {
+    pub min: Point3,
+    pub max: Point3,
+    front: XyRect<M>,
+    back: FlipFace<XyRect<M>>,
+    top: XzRect<M>,
+    bottom: FlipFace<XzRect<M>>,
+    right: YzRect<M>,
+    left: FlipFace<YzRect<M>>,
+}
+
+impl<M: Material + Clone> Cuboid<M> {
+    pub fn new(min: Point3, max: Point3, material: M) -> Self {
+        let (x, y, z) = ((min.x, max.x), (min.y, max.y), (min.z, max.z));
+        Cuboid {
+            min,
+            max,
+            front: XyRect::new(x, y, max.z, material.clone()),
+            back: FlipFace::new(XyRect::new(x, y, min.z, material.clone())),
+            top: XzRect::new(x, z, max.y, material.clone()),
+            bottom: FlipFace::new(XzRect::new(x, z, min.y, material.clone())),
+            right: YzRect::new(y, z, max.x, material.clone()),
+            left: FlipFace::new(YzRect::new(y, z, min.x, material)),
+        }
+    }
+}
+
+impl<M: Material> Hittable for Cuboid<M> {
+    fn hit(&self, ray: &Ray, (t_min, t_max): (f64, f64)) -> Option<HitRecord<'_>> {
+        let sides: [&dyn Hittable; 6] = [
+            &self.front,
+            &self.back,
+            &self.top,
+            &self.bottom,
+            &self.right,
+            &self.left,
+        ];
+
+        let mut closest_so_far: Option<HitRecord> = None;
+        let mut t_max = t_max;
+
+        for side in &sides {
+            if let Some(record) = side.hit(ray, (t_min, t_max)) {
+                t_max = record.t;
+                closest_so_far = Some(record);
+            }
+        }
+
+        closest_so_far
+    }
+
+    fn bounding_box(&self, _: f64, _: f64) -> Option<Aabb> {
+        Some(Aabb::new(self.min, self.max).padded(BOX_PADDING))
+    }
+}
+
+/// Intersects the rectangle spanning `(a0, a1)` and `(b0, b1)` along axes `a` and `b` in the plane
+/// where axis `c` equals `k`.
+#[inline]
+fn hit_rect<'a>(
+    ray: &Ray,
+    (t_min, t_max): (f64, f64),
+    (a, b, c): (usize, usize, usize),
+    (a0, a1): (f64, f64),
+    (b0, b1): (f64, f64),
+    k: f64,
+    material: &'a dyn Material,
+) -> Option<HitRecord<'a>> {
+    let t = (k - ray.origin[c]) / ray.direction[c];
+    // Written this way round so that a `NaN` from a parallel ray is also rejected.
+    if !(t > t_min && t < t_max) {
+        return None;
+    }
+
+    let point = ray.point_at(t);
+    if point[a] < a0 || point[a] > a1 || point[b] < b0 || point[b] > b1 {
+        return None;
+    }
+
+    let mut outward_normal = Vec3::zeros();
+    outward_normal[c] = 1.0;
+
+    Some(HitRecord::with_face_normal(
+        *ray,
+        point,
+        outward_normal,
+        material,
+        (point[a] - a0) / (a1 - a0),
+        (point[b] - b0) / (b1 - b0),
+        t,
+    ))
+}
+
+/// Returns a uniformly distributed point on the rectangle described as for `hit_rect()`.
+fn sample_rect(
+    (a, b, c): (usize, usize, usize),
+    (a0, a1): (f64, f64),
+    (b0, b1): (f64, f64),
+    k: f64,
+    rng: &mut dyn RngCore,
+) -> Point3 {
+    let mut point = Point3::zeros();
+    point[a] = a0 + rng.gen::<f64>() * (a1 - a0);
+    point[b] = b0 + rng.gen::<f64>() * (b1 - b0);
+    point[c] = k;
+    point
+}
+
+/// Converts the density of uniformly sampled points on a rectangle of the given `area` to the
+/// density of the direction from `origin` to them.
+fn rect_pdf_value<H: Hittable>(rect: &H, area: f64, origin: Point3, direction: Vec3) -> f64 {
+    match rect.hit(&Ray::new(origin, direction), SAMPLING_T_RANGE) {
+        Some(hit) => {
+            let distance_squared = hit.t.powi(2) * direction.len_squared();
+            let cosine = direction.dot(hit.normal).abs() / direction.len();
+            distance_squared / (cosine * area)
+        }
+        None => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::*;
+    use crate::mat::Lambertian;
+    use crate::random;
+
+    #[test]
+    fn rect_hit_has_uv_and_padded_box() {
+        let rect = XzRect::new((0.0, 2.0), (0.0, 4.0), 1.0, Lambertian::default());
+        let ray = Ray::new(Point3::new(0.5, 3.0, 1.0), Vec3::new(0.0, -1.0, 0.0));
+
+        let hit = rect.hit(&ray, (0.0, f64::MAX)).expect("ray missed");
+        assert_eq!(hit.t, 2.0);
+        assert_eq!(hit.normal, Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!((hit.texture_u, hit.texture_v), (0.25, 0.25));
+        assert!(rect
+            .bounding_box(0.0, 0.0)
+            .unwrap()
+            .hit(&ray, (0.0, f64::MAX)));
+    }
+
+    #[test]
+    fn cuboid_faces_point_outwards() {
+        let cuboid = Cuboid::new(Point3::zeros(), Point3::ones(), Lambertian::default());
+
+        for axis in 0..3 {
+            let mut origin = Point3::new(0.5, 0.5, 0.5);
+            origin[axis] = -1.0;
+            let mut direction = Vec3::zeros();
+            direction[axis] = 1.0;
+
+            let hit = cuboid
+                .hit(&Ray::new(origin, direction), (0.0, f64::MAX))
+                .expect("ray missed");
+            assert!(hit.is_front_face, "axis {} hit the inside of the box", axis);
+            assert_eq!(hit.t, 1.0);
+
+            let from_inside = Ray::new(Point3::new(0.5, 0.5, 0.5), direction);
+            let hit = cuboid.hit(&from_inside, (0.0, f64::MAX)).unwrap();
+            assert!(!hit.is_front_face);
+        }
+    }
+
+    #[test]
+    fn sampled_directions_cover_the_solid_angle() {
+        // Seen from one unit above its center, a 2x2 square subtends a third of the hemisphere.
+        let rect = FlipFace::new(XyRect::new(
+            (-1.0, 1.0),
+            (-1.0, 1.0),
+            0.0,
+            Lambertian::default(),
+        ));
+        let origin = Point3::new(0.0, 0.0, 1.0);
+        let mut rng = random::stream_rng(0, 0);
+
+        let samples = 20_000;
+        let mut solid_angle = 0.0;
+        for _ in 0..samples {
+            let direction = rect.sample_direction(origin, &mut rng).unwrap();
+            let pdf = rect.pdf_value(origin, direction);
+            assert!(pdf > 0.0);
+            solid_angle += 1.0 / pdf;
+        }
+        solid_angle /= samples as f64;
+        assert!(
+            (solid_angle - 2.0 * PI / 3.0).abs() < 0.02,
+            "{}",
+            solid_angle
+        );
+
+        assert_eq!(rect.pdf_value(origin, Vec3::new(0.0, 0.0, 1.0)), 0.0);
+        assert_eq!(rect.pdf_value(origin, Vec3::new(2.0, 0.0, -1.0)), 0.0);
+    }
+}
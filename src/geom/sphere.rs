@@ -1,4 +1,8 @@
-use super::{HitRecord, Hittable};
+use std::f64::consts::PI;
+
+use rand::{Rng, RngCore};
+
+use super::{HitRecord, Hittable, SAMPLING_T_RANGE};
 use crate::aabb::{self, Aabb};
 use crate::mat::Material;
 use crate::ray::Ray;
@@ -23,7 +27,7 @@ impl<M: Material> Sphere<M> {
 
 impl<M: Material> Hittable for Sphere<M> {
     #[inline(always)]
-    fn hit(&self, ray: &Ray, (t_min, t_max): (f64, f64)) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, (t_min, t_max): (f64, f64)) -> Option<HitRecord<'_>> {
         let origin_to_center = ray.origin - self.center;
         let a = ray.direction.len_squared();
         let half_b = origin_to_center.dot(ray.direction);
@@ -37,7 +41,7 @@ impl<M: Material> Hittable for Sphere<M> {
             if t < t_max && t > t_min {
                 let point = ray.point_at(t);
                 let outward_normal = (point - self.center) / self.radius;
-                let (u_coord, v_coord) = compute_sphere_uv(point - self.center);
+                let (u_coord, v_coord) = compute_sphere_uv(outward_normal);
                 return Some(HitRecord::with_face_normal(
                     *ray,
                     point,
@@ -53,7 +57,7 @@ impl<M: Material> Hittable for Sphere<M> {
             if t < t_max && t > t_min {
                 let point = ray.point_at(t);
                 let outward_normal = (point - self.center) / self.radius;
-                let (u_coord, v_coord) = compute_sphere_uv(point - self.center);
+                let (u_coord, v_coord) = compute_sphere_uv(outward_normal);
                 return Some(HitRecord::with_face_normal(
                     *ray,
                     point,
@@ -69,6 +73,34 @@ impl<M: Material> Hittable for Sphere<M> {
         None
     }
 
+    fn occluded(&self, ray: &Ray, t_range: (f64, f64)) -> bool {
+        hits_sphere(self.center, self.radius, ray, t_range)
+    }
+
+    /// Returns the density of directions sampled uniformly from the cone the sphere subtends.
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let ray = Ray::new(origin, direction);
+        match cos_theta_max(self.center, self.radius, origin) {
+            Some(cos_theta_max) if self.occluded(&ray, SAMPLING_T_RANGE) => {
+                1.0 / (2.0 * PI * (1.0 - cos_theta_max))
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// Picks a direction uniformly from the cone the sphere subtends, as long as `origin` lies
+    /// outside of it.
+    fn sample_direction(&self, origin: Point3, rng: &mut dyn RngCore) -> Option<Vec3> {
+        let cos_theta_max = cos_theta_max(self.center, self.radius, origin)?;
+        let w = (self.center - origin).to_unit();
+        let (u, v) = w.orthonormal_basis();
+
+        let phi = 2.0 * PI * rng.gen::<f64>();
+        let z = 1.0 + rng.gen::<f64>() * (cos_theta_max - 1.0);
+        let r = (1.0 - z.powi(2)).sqrt();
+        Some(r * phi.cos() * u + r * phi.sin() * v + z * w)
+    }
+
     fn bounding_box(&self, _: f64, _: f64) -> Option<Aabb> {
         Some(Aabb {
             min: self.center - Vec3::new(self.radius, self.radius, self.radius),
@@ -103,7 +135,7 @@ impl<M: Material> MovingSphere<M> {
 
 impl<M: Material> Hittable for MovingSphere<M> {
     #[inline(always)]
-    fn hit(&self, ray: &Ray, (t_min, t_max): (f64, f64)) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, (t_min, t_max): (f64, f64)) -> Option<HitRecord<'_>> {
         let origin_to_center = ray.origin - self.center_at(ray.time);
         let a = ray.direction.len_squared();
         let half_b = origin_to_center.dot(ray.direction);
@@ -117,7 +149,7 @@ impl<M: Material> Hittable for MovingSphere<M> {
             if t < t_max && t > t_min {
                 let point = ray.point_at(t);
                 let outward_normal = (point - self.center_at(ray.time)) / self.radius;
-                let (u_coord, v_coord) = compute_sphere_uv(point - self.center_at(ray.time));
+                let (u_coord, v_coord) = compute_sphere_uv(outward_normal);
                 return Some(HitRecord::with_face_normal(
                     *ray,
                     point,
@@ -133,7 +165,7 @@ impl<M: Material> Hittable for MovingSphere<M> {
             if t < t_max && t > t_min {
                 let point = ray.point_at(t);
                 let outward_normal = (point - self.center_at(ray.time)) / self.radius;
-                let (u_coord, v_coord) = compute_sphere_uv(point - self.center_at(ray.time));
+                let (u_coord, v_coord) = compute_sphere_uv(outward_normal);
                 return Some(HitRecord::with_face_normal(
                     *ray,
                     point,
@@ -149,6 +181,10 @@ impl<M: Material> Hittable for MovingSphere<M> {
         None
     }
 
+    fn occluded(&self, ray: &Ray, t_range: (f64, f64)) -> bool {
+        hits_sphere(self.center_at(ray.time), self.radius, ray, t_range)
+    }
+
     fn bounding_box(&self, time0: f64, time1: f64) -> Option<Aabb> {
         let box0 = Aabb {
             min: self.center_at(time0) - Vec3::new(self.radius, self.radius, self.radius),
@@ -163,6 +199,35 @@ impl<M: Material> Hittable for MovingSphere<M> {
     }
 }
 
+/// Tests whether either root of the ray/sphere intersection lies within `(t_min, t_max)`.
+#[inline]
+fn hits_sphere(center: Point3, radius: f64, ray: &Ray, (t_min, t_max): (f64, f64)) -> bool {
+    let origin_to_center = ray.origin - center;
+    let a = ray.direction.len_squared();
+    let half_b = origin_to_center.dot(ray.direction);
+    let c = origin_to_center.len_squared() - radius.powi(2);
+    let discriminant = half_b.powi(2) - a * c;
+
+    if discriminant > 0.0 {
+        let root = discriminant.sqrt();
+        let near = (-half_b - root) / a;
+        let far = (-half_b + root) / a;
+        (near < t_max && near > t_min) || (far < t_max && far > t_min)
+    } else {
+        false
+    }
+}
+
+/// Returns the cosine of the half-angle of the cone a sphere subtends from `origin`, or `None` if
+/// `origin` is inside the sphere.
+fn cos_theta_max(center: Point3, radius: f64, origin: Point3) -> Option<f64> {
+    let distance_squared = (center - origin).len_squared();
+    if distance_squared <= radius.powi(2) {
+        return None;
+    }
+    Some((1.0 - radius.powi(2) / distance_squared).sqrt())
+}
+
 fn compute_sphere_uv(p: Vec3) -> (f64, f64) {
     let phi = p.z.atan2(p.x);
     let theta = p.y.asin();
@@ -170,3 +235,29 @@ fn compute_sphere_uv(p: Vec3) -> (f64, f64) {
     let v = (theta + std::f64::consts::PI / 2.0) / std::f64::consts::PI;
     (u, v)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mat::Lambertian;
+    use crate::random;
+
+    #[test]
+    fn samples_the_subtended_cone() {
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, -2.0), 1.0, Lambertian::default());
+        let origin = Point3::zeros();
+        // The cone's half-angle is 30°, so it covers 2π(1 - cos 30°) steradians.
+        let expected = 1.0 / (2.0 * PI * (1.0 - 0.75f64.sqrt()));
+        let mut rng = random::stream_rng(0, 0);
+
+        for _ in 0..100 {
+            let direction = sphere.sample_direction(origin, &mut rng).unwrap();
+            assert!(direction.to_unit().dot(Vec3::new(0.0, 0.0, -1.0)) >= 0.75f64.sqrt() - 1e-9);
+            assert!((sphere.pdf_value(origin, direction) - expected).abs() < 1e-9);
+        }
+        assert_eq!(sphere.pdf_value(origin, Vec3::new(0.0, 1.0, 0.0)), 0.0);
+        assert!(sphere
+            .sample_direction(Point3::new(0.0, 0.0, -2.5), &mut rng)
+            .is_none());
+    }
+}
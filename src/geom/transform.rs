@@ -0,0 +1,409 @@
+use std::sync::Arc;
+
+use anyhow::format_err;
+use rand::RngCore;
+
+use super::{HitRecord, Hittable};
+use crate::aabb::{self, Aabb};
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+
+/// An affine transformation, stored as a 3x3 linear part followed by a translation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Affine {
+    pub linear: [[f64; 3]; 3],
+    pub translation: Vec3,
+}
+
+impl Affine {
+    pub const fn identity() -> Self {
+        Affine {
+            linear: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            translation: Vec3::zeros(),
+        }
+    }
+
+    pub const fn translation(offset: Vec3) -> Self {
+        Affine {
+            linear: Affine::identity().linear,
+            translation: offset,
+        }
+    }
+
+    /// Rotation by `angle_deg` degrees counter-clockwise about `axis`, following the right-hand rule.
+    ///
+    /// A zero or non-finite axis gives a matrix of NaNs, which `inverse()` and so `Transform::new()`
+    /// reject.
+    pub fn rotation(axis: Vec3, angle_deg: f64) -> Self {
+        let Vec3 { x, y, z } = axis.to_unit();
+        let (sin, cos) = angle_deg.to_radians().sin_cos();
+        let t = 1.0 - cos;
+
+        Affine {
+            linear: [
+                [t * x * x + cos, t * x * y - sin * z, t * x * z + sin * y],
+                [t * x * y + sin * z, t * y * y + cos, t * y * z - sin * x],
+                [t * x * z - sin * y, t * y * z + sin * x, t * z * z + cos],
+            ],
+            translation: Vec3::zeros(),
+        }
+    }
+
+    pub const fn scaling(factors: Vec3) -> Self {
+        Affine {
+            linear: [
+                [factors.x, 0.0, 0.0],
+                [0.0, factors.y, 0.0],
+                [0.0, 0.0, factors.z],
+            ],
+            translation: Vec3::zeros(),
+        }
+    }
+
+    /// Returns the transformation that applies `self` first and then `next`.
+    pub fn then(self, next: Affine) -> Self {
+        let mut linear = [[0.0; 3]; 3];
+        for (i, row) in linear.iter_mut().enumerate() {
+            for (j, cell) in row.iter_mut().enumerate() {
+                *cell = (0..3).map(|k| next.linear[i][k] * self.linear[k][j]).sum();
+            }
+        }
+
+        Affine {
+            linear,
+            translation: next.transform_point(self.translation),
+        }
+    }
+
+    pub fn inverse(&self) -> Option<Self> {
+        let m = &self.linear;
+        let cofactor = |r0: usize, r1: usize, c0: usize, c1: usize| {
+            m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]
+        };
+
+        let adjugate = [
+            [
+                cofactor(1, 2, 1, 2),
+                -cofactor(0, 2, 1, 2),
+                cofactor(0, 1, 1, 2),
+            ],
+            [
+                -cofactor(1, 2, 0, 2),
+                cofactor(0, 2, 0, 2),
+                -cofactor(0, 1, 0, 2),
+            ],
+            [
+                cofactor(1, 2, 0, 1),
+                -cofactor(0, 2, 0, 1),
+                cofactor(0, 1, 0, 1),
+            ],
+        ];
+
+        let det = m[0][0] * adjugate[0][0] + m[0][1] * adjugate[1][0] + m[0][2] * adjugate[2][0];
+        if det == 0.0 || !det.is_finite() || !self.translation.is_finite() {
+            return None;
+        }
+
+        let mut linear = adjugate;
+        linear.iter_mut().flatten().for_each(|cell| *cell /= det);
+
+        let inverse_linear = Affine {
+            linear,
+            translation: Vec3::zeros(),
+        };
+
+        Some(Affine {
+            linear,
+            translation: -inverse_linear.transform_vector(self.translation),
+        })
+    }
+
+    /// Returns the determinant of the linear part, i.e. the factor by which volumes are scaled.
+    pub fn determinant(&self) -> f64 {
+        let m = &self.linear;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    #[inline]
+    pub fn transform_point(&self, point: Point3) -> Point3 {
+        self.transform_vector(point) + self.translation
+    }
+
+    #[inline]
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.linear;
+        Vec3::new(
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+        )
+    }
+
+    /// Multiplies `v` by the transpose of the linear part.
+    ///
+    /// Called on the inverse transform, this maps surface normals out of object space.
+    #[inline]
+    pub fn transform_vector_transposed(&self, v: Vec3) -> Vec3 {
+        let m = &self.linear;
+        Vec3::new(
+            m[0][0] * v.x + m[1][0] * v.y + m[2][0] * v.z,
+            m[0][1] * v.x + m[1][1] * v.y + m[2][1] * v.z,
+            m[0][2] * v.x + m[1][2] * v.y + m[2][2] * v.z,
+        )
+    }
+}
+
+impl Default for Affine {
+    fn default() -> Self {
+        Affine::identity()
+    }
+}
+
+/// Places an object in the world with an arbitrary affine transformation.
+///
+/// Incoming rays are mapped into the object's own coordinate space, so the wrapped object is
+/// never modified and may itself be a `Bvh`, mesh or another `Transform`. Either a
+/// `Box<dyn Hittable>` or an `Arc<dyn Hittable>` may be wrapped, the latter allowing the same
+/// object to be placed at several poses.
+#[derive(Debug)]
+pub struct Transform {
+    inner: Arc<dyn Hittable>,
+    object_to_world: Affine,
+    world_to_object: Affine,
+}
+
+impl Transform {
+    pub fn new<H: Into<Arc<dyn Hittable>>>(
+        inner: H,
+        object_to_world: Affine,
+    ) -> anyhow::Result<Self> {
+        let world_to_object = object_to_world
+            .inverse()
+            .ok_or_else(|| format_err!("Transform is not invertible: {:?}", object_to_world))?;
+
+        Ok(Transform {
+            inner: inner.into(),
+            object_to_world,
+            world_to_object,
+        })
+    }
+
+    pub fn translate<H: Into<Arc<dyn Hittable>>>(inner: H, offset: Vec3) -> anyhow::Result<Self> {
+        Transform::new(inner, Affine::translation(offset))
+    }
+
+    pub fn rotate<H: Into<Arc<dyn Hittable>>>(
+        inner: H,
+        axis: Vec3,
+        angle_deg: f64,
+    ) -> anyhow::Result<Self> {
+        Transform::new(inner, Affine::rotation(axis, angle_deg))
+    }
+
+    pub fn scale<H: Into<Arc<dyn Hittable>>>(inner: H, factors: Vec3) -> anyhow::Result<Self> {
+        Transform::new(inner, Affine::scaling(factors))
+    }
+
+    pub fn object_to_world(&self) -> &Affine {
+        &self.object_to_world
+    }
+
+    fn to_object_space(&self, ray: &Ray) -> Ray {
+        // The direction is deliberately left unnormalized so that `t` agrees in both spaces.
+        Ray::with_time(
+            self.world_to_object.transform_point(ray.origin),
+            self.world_to_object.transform_vector(ray.direction),
+            ray.time,
+        )
+    }
+}
+
+impl Hittable for Transform {
+    fn hit(&self, ray: &Ray, t_range: (f64, f64)) -> Option<HitRecord<'_>> {
+        let mut record = self.inner.hit(&self.to_object_space(ray), t_range)?;
+        record.point = self.object_to_world.transform_point(record.point);
+        record.normal = self
+            .world_to_object
+            .transform_vector_transposed(record.normal)
+            .to_unit();
+        Some(record)
+    }
+
+    fn occluded(&self, ray: &Ray, t_range: (f64, f64)) -> bool {
+        self.inner.occluded(&self.to_object_space(ray), t_range)
+    }
+
+    /// Converts the wrapped object's density, since the linear part of the transformation
+    /// stretches solid angles unevenly.
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        // A unit direction `d` maps to `N d` for the linear part `N` of `world_to_object`, which
+        // scales solid angles around it by `|det N| / |N d|³`.
+        let object_direction = self.world_to_object.transform_vector(direction.to_unit());
+        let density = self.inner.pdf_value(
+            self.world_to_object.transform_point(origin),
+            object_direction,
+        );
+        density * self.world_to_object.determinant().abs() / object_direction.len().powi(3)
+    }
+
+    fn sample_direction(&self, origin: Point3, rng: &mut dyn RngCore) -> Option<Vec3> {
+        let direction = self
+            .inner
+            .sample_direction(self.world_to_object.transform_point(origin), rng)?;
+        Some(self.object_to_world.transform_vector(direction))
+    }
+
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<Aabb> {
+        let object_box = self.inner.bounding_box(time0, time1)?;
+        let corner = |i: usize| {
+            let pick = |axis: usize| {
+                if i & (1 << axis) == 0 {
+                    object_box.min[axis]
+                } else {
+                    object_box.max[axis]
+                }
+            };
+            let p = self
+                .object_to_world
+                .transform_point(Point3::new(pick(0), pick(1), pick(2)));
+            Aabb::new(p, p)
+        };
+
+        Some((1..8).map(corner).fold(corner(0), aabb::surrounding_box))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use super::*;
+    use crate::geom::{Sphere, XyRect};
+    use crate::mat::Lambertian;
+    use crate::random;
+
+    fn assert_vec_eq(actual: Vec3, expected: Vec3) {
+        assert_float_eq!(actual.x, expected.x, abs <= 1e-9);
+        assert_float_eq!(actual.y, expected.y, abs <= 1e-9);
+        assert_float_eq!(actual.z, expected.z, abs <= 1e-9);
+    }
+
+    #[test]
+    fn composition_and_inverse() {
+        let affine = Affine::scaling(Vec3::new(2.0, 3.0, 4.0))
+            .then(Affine::rotation(Vec3::new(0.0, 1.0, 0.0), 90.0))
+            .then(Affine::translation(Vec3::new(1.0, 0.0, 0.0)));
+
+        let point = Point3::new(1.0, 1.0, 1.0);
+        assert_vec_eq(affine.transform_point(point), Point3::new(5.0, 3.0, -2.0));
+
+        let roundtrip = affine
+            .inverse()
+            .unwrap()
+            .transform_point(affine.transform_point(point));
+        assert_vec_eq(roundtrip, point);
+        assert!(Affine::scaling(Vec3::new(1.0, 0.0, 1.0))
+            .inverse()
+            .is_none());
+    }
+
+    #[test]
+    fn rejects_degenerate_rotations_and_translations() {
+        let sphere = || -> Box<dyn Hittable> {
+            Box::new(Sphere::new(Point3::zeros(), 1.0, Lambertian::default()))
+        };
+        assert!(Transform::rotate(sphere(), Vec3::zeros(), 30.0).is_err());
+        assert!(Transform::rotate(sphere(), Vec3::new(f64::NAN, 0.0, 1.0), 30.0).is_err());
+        assert!(Transform::rotate(sphere(), Vec3::new(0.0, 1.0, 0.0), f64::INFINITY).is_err());
+        assert!(Transform::translate(sphere(), Vec3::new(0.0, f64::INFINITY, 0.0)).is_err());
+        assert!(Transform::rotate(sphere(), Vec3::new(0.0, 1.0, 0.0), 30.0).is_ok());
+    }
+
+    #[test]
+    fn scaled_sphere_hit_and_bounds() {
+        let sphere: Box<dyn Hittable> =
+            Box::new(Sphere::new(Point3::zeros(), 1.0, Lambertian::default()));
+        let ellipsoid: Box<dyn Hittable> =
+            Box::new(Transform::scale(sphere, Vec3::new(1.0, 2.0, 1.0)).unwrap());
+        let moved = Transform::translate(ellipsoid, Vec3::new(0.0, 0.0, -5.0)).unwrap();
+
+        let ray = Ray::new(Point3::new(0.0, 10.0, -5.0), Vec3::new(0.0, -1.0, 0.0));
+        let hit = moved.hit(&ray, (0.0, f64::MAX)).expect("ray missed");
+        assert_float_eq!(hit.t, 8.0, abs <= 1e-9);
+        assert_vec_eq(hit.point, Point3::new(0.0, 2.0, -5.0));
+        assert_vec_eq(hit.normal, Vec3::new(0.0, 1.0, 0.0));
+
+        let bounds = moved.bounding_box(0.0, 0.0).unwrap();
+        assert_vec_eq(bounds.min, Point3::new(-1.0, -2.0, -6.0));
+        assert_vec_eq(bounds.max, Point3::new(1.0, 2.0, -4.0));
+    }
+
+    #[test]
+    fn shared_object_at_two_poses() {
+        let sphere: Arc<dyn Hittable> =
+            Arc::new(Sphere::new(Point3::zeros(), 1.0, Lambertian::default()));
+        let left = Transform::translate(sphere.clone(), Vec3::new(-2.0, 0.0, 0.0)).unwrap();
+        let right = Transform::translate(sphere, Vec3::new(2.0, 0.0, 0.0)).unwrap();
+
+        let ray = Ray::new(Point3::new(-2.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(left.hit(&ray, (0.0, f64::MAX)).is_some());
+        assert!(right.hit(&ray, (0.0, f64::MAX)).is_none());
+    }
+
+    #[test]
+    fn samples_transformed_lights() {
+        let rect: Box<dyn Hittable> = Box::new(XyRect::new(
+            (0.0, 1.0),
+            (0.0, 1.0),
+            -1.0,
+            Lambertian::default(),
+        ));
+        let affine = Affine::scaling(Vec3::new(3.0, 2.0, 2.0))
+            .then(Affine::rotation(Vec3::new(1.0, 1.0, 0.0), 30.0));
+        let transformed = Transform::new(rect, affine).unwrap();
+
+        // The same rectangle, built directly in world space from its transformed corners.
+        let expected = Transform::rotate(
+            Box::new(XyRect::new(
+                (0.0, 3.0),
+                (0.0, 2.0),
+                -2.0,
+                Lambertian::default(),
+            )) as Box<dyn Hittable>,
+            Vec3::new(1.0, 1.0, 0.0),
+            30.0,
+        )
+        .unwrap();
+
+        let origin = Point3::new(0.5, 0.5, 3.0);
+        let mut rng = random::stream_rng(0, 0);
+        for _ in 0..100 {
+            let direction = transformed.sample_direction(origin, &mut rng).unwrap();
+            let density = expected.pdf_value(origin, direction);
+            assert!(density > 0.0);
+            assert_float_eq!(
+                transformed.pdf_value(origin, direction),
+                density,
+                rel <= 1e-9
+            );
+        }
+    }
+
+    #[test]
+    fn rotated_normals_stay_perpendicular() {
+        let sphere: Box<dyn Hittable> = Box::new(Sphere::new(
+            Point3::new(2.0, 0.0, 0.0),
+            1.0,
+            Lambertian::default(),
+        ));
+        let rotated = Transform::rotate(sphere, Vec3::new(0.0, 0.0, 1.0), 90.0).unwrap();
+
+        let ray = Ray::new(Point3::new(0.0, 10.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let hit = rotated.hit(&ray, (0.0, f64::MAX)).expect("ray missed");
+        assert_vec_eq(hit.point, Point3::new(0.0, 3.0, 0.0));
+        assert_vec_eq(hit.normal, Vec3::new(0.0, 1.0, 0.0));
+        assert!(hit.is_front_face);
+    }
+}
@@ -0,0 +1,203 @@
+use super::{HitRecord, Hittable};
+use crate::aabb::{self, Aabb};
+use crate::mat::Material;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+
+const EPSILON: f64 = 1e-9;
+const BOX_PADDING: f64 = 1e-4;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Triangle<M: Material> {
+    pub vertices: [Point3; 3],
+    pub normals: Option<[Vec3; 3]>,
+    pub uvs: [(f64, f64); 3],
+    pub material: M,
+}
+
+impl<M: Material> Triangle<M> {
+    pub fn new(vertices: [Point3; 3], material: M) -> Self {
+        Triangle {
+            vertices,
+            normals: None,
+            uvs: [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)],
+            material,
+        }
+    }
+
+    /// Sets per-vertex shading normals, which are interpolated across the face.
+    pub fn with_normals(mut self, normals: [Vec3; 3]) -> Self {
+        self.normals = Some(normals);
+        self
+    }
+
+    /// Sets per-vertex texture coordinates, which are interpolated across the face.
+    pub fn with_uvs(mut self, uvs: [(f64, f64); 3]) -> Self {
+        self.uvs = uvs;
+        self
+    }
+}
+
+impl<M: Material> Hittable for Triangle<M> {
+    fn hit(&self, ray: &Ray, t_range: (f64, f64)) -> Option<HitRecord<'_>> {
+        let normals = self.normals.as_ref();
+        hit_triangle(
+            &self.vertices,
+            normals,
+            &self.uvs,
+            &self.material,
+            ray,
+            t_range,
+        )
+    }
+
+    fn bounding_box(&self, _: f64, _: f64) -> Option<Aabb> {
+        Some(triangle_bounding_box(&self.vertices))
+    }
+}
+
+/// Möller–Trumbore ray/triangle intersection.
+///
+/// The face orientation is always derived from the geometric normal (counter-clockwise winding),
+/// while the shading normal is interpolated from `normals` when present and flipped to agree with
+/// it.
+#[inline]
+pub(crate) fn hit_triangle<'a>(
+    [p0, p1, p2]: &[Point3; 3],
+    normals: Option<&[Vec3; 3]>,
+    uvs: &[(f64, f64); 3],
+    material: &'a dyn Material,
+    ray: &Ray,
+    (t_min, t_max): (f64, f64),
+) -> Option<HitRecord<'a>> {
+    let edge1 = *p1 - *p0;
+    let edge2 = *p2 - *p0;
+    let p_vec = ray.direction.cross(edge2);
+    let det = edge1.dot(p_vec);
+
+    // Scale the parallel test by the edge lengths so that tiny and huge triangles behave alike.
+    if det.abs() < EPSILON * edge1.len() * edge2.len() * ray.direction.len() {
+        return None;
+    }
+
+    let inv_det = 1.0 / det;
+    let t_vec = ray.origin - *p0;
+    let b1 = t_vec.dot(p_vec) * inv_det;
+    if !(0.0..=1.0).contains(&b1) {
+        return None;
+    }
+
+    let q_vec = t_vec.cross(edge1);
+    let b2 = ray.direction.dot(q_vec) * inv_det;
+    if b2 < 0.0 || b1 + b2 > 1.0 {
+        return None;
+    }
+
+    let t = edge2.dot(q_vec) * inv_det;
+    if t >= t_max || t <= t_min {
+        return None;
+    }
+
+    let b0 = 1.0 - b1 - b2;
+    let geometric_normal = edge1.cross(edge2).to_unit();
+    let is_front_face = ray.direction.dot(geometric_normal) < 0.0;
+    let facing_normal = if is_front_face {
+        geometric_normal
+    } else {
+        -geometric_normal
+    };
+
+    let normal = match normals {
+        Some([n0, n1, n2]) => {
+            let interpolated = (b0 * *n0 + b1 * *n1 + b2 * *n2).to_unit();
+            if interpolated.dot(facing_normal) < 0.0 {
+                -interpolated
+            } else {
+                interpolated
+            }
+        }
+        None => facing_normal,
+    };
+
+    let texture_u = b0 * uvs[0].0 + b1 * uvs[1].0 + b2 * uvs[2].0;
+    let texture_v = b0 * uvs[0].1 + b1 * uvs[1].1 + b2 * uvs[2].1;
+
+    Some(HitRecord::new(
+        ray.point_at(t),
+        normal,
+        material,
+        texture_u,
+        texture_v,
+        t,
+        is_front_face,
+    ))
+}
+
+#[inline]
+pub(crate) fn triangle_bounding_box([p0, p1, p2]: &[Point3; 3]) -> Aabb {
+    let edge = aabb::surrounding_box(Aabb::new(*p0, *p0), Aabb::new(*p1, *p1));
+    aabb::surrounding_box(edge, Aabb::new(*p2, *p2)).padded(BOX_PADDING)
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use super::*;
+    use crate::mat::Lambertian;
+    use crate::vec3::Color;
+
+    fn unit_triangle() -> Triangle<Lambertian<Color>> {
+        Triangle::new(
+            [
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0),
+            ],
+            Lambertian::default(),
+        )
+    }
+
+    #[test]
+    fn hits_front_face_with_interpolated_uv() {
+        let triangle = unit_triangle().with_uvs([(0.2, 0.9), (0.6, 0.1), (1.0, 0.3)]);
+        // The barycentric coordinates of the hit point are (0.25, 0.25, 0.5).
+        let ray = Ray::new(Point3::new(0.25, 0.5, 1.0), Vec3::new(0.0, 0.0, -1.0));
+
+        let hit = triangle.hit(&ray, (0.0, f64::MAX)).expect("ray missed");
+        assert!(hit.is_front_face);
+        assert_eq!(hit.normal, Vec3::new(0.0, 0.0, 1.0));
+        assert_float_eq!(hit.t, 1.0, abs <= 1e-12);
+        assert_float_eq!(hit.texture_u, 0.7, abs <= 1e-12);
+        assert_float_eq!(hit.texture_v, 0.4, abs <= 1e-12);
+    }
+
+    #[test]
+    fn back_face_flips_shading_normal() {
+        let triangle = unit_triangle().with_normals([Vec3::new(0.0, 0.0, 1.0); 3]);
+        let ray = Ray::new(Point3::new(0.2, 0.2, -1.0), Vec3::new(0.0, 0.0, 1.0));
+
+        let hit = triangle.hit(&ray, (0.0, f64::MAX)).expect("ray missed");
+        assert!(!hit.is_front_face);
+        assert_eq!(hit.normal, Vec3::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn misses_outside_edges_and_parallel_rays() {
+        let triangle = unit_triangle();
+        let outside = Ray::new(Point3::new(0.75, 0.75, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let parallel = Ray::new(Point3::new(-1.0, 0.2, 0.0), Vec3::new(1.0, 0.0, 0.0));
+
+        assert!(triangle.hit(&outside, (0.0, f64::MAX)).is_none());
+        assert!(triangle.hit(&parallel, (0.0, f64::MAX)).is_none());
+    }
+
+    #[test]
+    fn planar_bounding_box_is_padded() {
+        let bounds = unit_triangle().bounding_box(0.0, 0.0).unwrap();
+        let ray = Ray::new(Point3::new(0.2, 0.2, 1.0), Vec3::new(0.0, 0.0, -1.0));
+
+        assert!(bounds.max.z > bounds.min.z);
+        assert!(bounds.hit(&ray, (0.0, f64::MAX)));
+    }
+}
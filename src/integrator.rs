@@ -0,0 +1,30 @@
+pub use self::debug::DebugIntegrator;
+pub use self::path::PathTracer;
+
+use std::fmt::Debug;
+
+use rand::RngCore;
+
+use crate::ray::Ray;
+use crate::scene::{Scene, Sky};
+use crate::vec3::Color;
+
+mod debug;
+mod path;
+
+/// Smallest distance along a ray at which hits count, so that rays leaving a surface do not hit
+/// it again due to rounding errors.
+pub(crate) const MIN_HIT_DISTANCE: f64 = 0.001;
+
+/// A strategy for estimating the light arriving along camera rays.
+pub trait Integrator<S: Sky>: Debug + Send + Sync {
+    /// Returns an estimate of the radiance arriving at the origin of `ray` from its direction,
+    /// drawing any random numbers needed from `rng`.
+    fn radiance(&self, scene: &Scene<S>, ray: &Ray, rng: &mut dyn RngCore) -> Color;
+}
+
+impl<S: Sky, I: Integrator<S> + ?Sized> Integrator<S> for Box<I> {
+    fn radiance(&self, scene: &Scene<S>, ray: &Ray, rng: &mut dyn RngCore) -> Color {
+        (**self).radiance(scene, ray, rng)
+    }
+}
@@ -0,0 +1,124 @@
+use std::str::FromStr;
+
+use anyhow::format_err;
+use rand::RngCore;
+
+use super::{Integrator, MIN_HIT_DISTANCE};
+use crate::geom::Hittable;
+use crate::ray::Ray;
+use crate::scene::{Scene, Sky};
+use crate::vec3::Color;
+
+/// Integrators showing a single property of the first surface each camera ray hits, for
+/// diagnosing problems with geometry and materials. Rays hitting nothing are black.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebugIntegrator {
+    /// The surface normal facing the ray, mapped from `-1.0..=1.0` to `0.0..=1.0` per axis.
+    Normals,
+    /// The texture coordinates, as red and green.
+    Uv,
+    /// The distance along the ray, in world units. These exceed one, so they are best saved to
+    /// PFM or darkened with a negative exposure.
+    Depth,
+    /// The surface color given by `Material::albedo()`, or the emitted radiance for lights.
+    Albedo,
+    /// White wherever anything was hit.
+    HitMiss,
+}
+
+impl FromStr for DebugIntegrator {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "normals" => Ok(DebugIntegrator::Normals),
+            "uv" => Ok(DebugIntegrator::Uv),
+            "depth" => Ok(DebugIntegrator::Depth),
+            "albedo" => Ok(DebugIntegrator::Albedo),
+            "hit-miss" => Ok(DebugIntegrator::HitMiss),
+            _ => Err(format_err!(
+                "Unknown debug integrator `{}`, expected one of: normals, uv, depth, albedo, \
+                 hit-miss",
+                s
+            )),
+        }
+    }
+}
+
+impl<S: Sky> Integrator<S> for DebugIntegrator {
+    fn radiance(&self, scene: &Scene<S>, ray: &Ray, _: &mut dyn RngCore) -> Color {
+        let hit = match scene.world.hit(ray, (MIN_HIT_DISTANCE, f64::MAX)) {
+            Some(hit) => hit,
+            None => return Color::zeros(),
+        };
+
+        match self {
+            DebugIntegrator::Normals => 0.5 * (hit.normal.to_unit() + Color::ones()),
+            DebugIntegrator::Uv => Color::new(hit.texture_u, hit.texture_v, 0.0),
+            DebugIntegrator::Depth => hit.t * ray.direction.len() * Color::ones(),
+            DebugIntegrator::Albedo => hit.material.albedo(&hit),
+            DebugIntegrator::HitMiss => Color::ones(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geom::Sphere;
+    use crate::mat::{DiffuseLight, Lambertian};
+    use crate::random;
+    use crate::scene::GradientSky;
+    use crate::vec3::{Point3, Vec3};
+
+    #[test]
+    fn shows_first_hit() {
+        let world: Vec<Box<dyn Hittable>> = vec![
+            Box::new(Sphere::new(
+                Point3::zeros(),
+                1.0,
+                Lambertian::new(Color::new(0.2, 0.4, 0.6)),
+            )),
+            Box::new(Sphere::new(
+                Point3::new(0.0, 0.0, -10.0),
+                1.0,
+                DiffuseLight::new(Color::new(3.0, 3.0, 3.0)),
+            )),
+        ];
+        let scene = Scene::new(world, GradientSky::default());
+        let mut rng = random::stream_rng(0, 0);
+        let towards = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -2.0));
+        let away = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, 1.0));
+        let radiance = |integrator: DebugIntegrator, ray, rng: &mut random::SampleRng| {
+            integrator.radiance(&scene, ray, rng)
+        };
+
+        assert_eq!(
+            radiance(DebugIntegrator::Normals, &towards, &mut rng),
+            Color::new(0.5, 0.5, 1.0)
+        );
+        assert_eq!(
+            radiance(DebugIntegrator::Depth, &towards, &mut rng),
+            Color::new(4.0, 4.0, 4.0)
+        );
+        assert_eq!(
+            radiance(DebugIntegrator::Albedo, &towards, &mut rng),
+            Color::new(0.2, 0.4, 0.6)
+        );
+        assert_eq!(
+            radiance(DebugIntegrator::HitMiss, &towards, &mut rng),
+            Color::ones()
+        );
+        assert_eq!(
+            radiance(DebugIntegrator::HitMiss, &away, &mut rng),
+            Color::zeros()
+        );
+
+        // Lights show their emission as their albedo.
+        let behind = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert_eq!(
+            radiance(DebugIntegrator::Albedo, &behind, &mut rng),
+            Color::new(3.0, 3.0, 3.0)
+        );
+    }
+}
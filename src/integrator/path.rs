@@ -0,0 +1,265 @@
+use rand::{Rng, RngCore};
+
+use super::{Integrator, MIN_HIT_DISTANCE};
+use crate::geom::{HitRecord, Hittable};
+use crate::mat::Scatter;
+use crate::ray::Ray;
+use crate::scene::{Scene, Sky};
+use crate::vec3::Color;
+
+/// A path tracer following one randomly scattered ray per bounce, up to the scene's
+/// `max_bounce_depth`.
+///
+/// After `min_bounce_depth` bounces, paths are terminated at random with Russian roulette: a path
+/// survives with a probability given by the fraction of light it still carries, and survivors are
+/// scaled up by its inverse to keep the estimate unbiased. This spends less time on paths that
+/// contribute almost nothing.
+///
+/// With light sampling enabled, which is the default, every bounce off a surface that isn't
+/// specular also samples one of the scene's `lights` directly, casting a shadow ray towards it
+/// (next-event estimation). Small, bright lights then converge much faster than when paths have to
+/// stumble upon them. Since the scattered ray may hit the same lights, both estimates are combined
+/// with multiple importance sampling: each is weighted by the power heuristic, favouring whichever
+/// of the light and the material was more likely to pick the direction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PathTracer {
+    pub light_sampling: bool,
+}
+
+impl PathTracer {
+    pub fn with_light_sampling(mut self, val: bool) -> Self {
+        self.light_sampling = val;
+        self
+    }
+
+    fn ray_color<S: Sky>(&self, scene: &Scene<S>, ray: &Ray, rng: &mut dyn RngCore) -> Color {
+        let mut color = Color::zeros();
+        // The fraction of light reaching the camera along the path so far.
+        let mut throughput = Color::ones();
+        let mut ray = *ray;
+        let mut emission_weight = 1.0;
+
+        for bounce in 0..scene.max_bounce_depth {
+            let hit_record = match scene.world.hit(&ray, (MIN_HIT_DISTANCE, f64::MAX)) {
+                Some(hit_record) => hit_record,
+                None => {
+                    color += throughput * scene.sky.color(&ray);
+                    break;
+                }
+            };
+
+            let emitted = hit_record.material.emitted(&ray, &hit_record);
+            color += emission_weight * throughput * emitted;
+            let Scatter {
+                ray: scattered,
+                attenuation,
+                pdf,
+                is_specular,
+            } = match hit_record.material.scatter(&ray, &hit_record, rng) {
+                Some(scatter) => scatter,
+                None => break,
+            };
+
+            emission_weight = 1.0;
+            if self.light_sampling && !is_specular && !scene.lights.is_empty() {
+                color += throughput * sample_light(scene, &ray, &hit_record, rng);
+                let light_pdf = scene
+                    .lights
+                    .pdf_value(hit_record.point, scattered.direction);
+                emission_weight = power_heuristic(pdf, light_pdf);
+            }
+
+            throughput = throughput * attenuation;
+            if bounce + 1 >= scene.min_bounce_depth {
+                let survival = throughput.x.max(throughput.y).max(throughput.z).min(1.0);
+                if survival <= 0.0 || rng.gen::<f64>() >= survival {
+                    break;
+                }
+                throughput /= survival;
+            }
+            ray = scattered;
+        }
+
+        color
+    }
+}
+
+impl Default for PathTracer {
+    fn default() -> Self {
+        PathTracer {
+            light_sampling: true,
+        }
+    }
+}
+
+impl<S: Sky> Integrator<S> for PathTracer {
+    fn radiance(&self, scene: &Scene<S>, ray: &Ray, rng: &mut dyn RngCore) -> Color {
+        self.ray_color(scene, ray, rng)
+    }
+}
+
+/// Estimates the light reaching `hit` directly from one of the scene's lights and scattered along
+/// `incoming`, weighted for combining it with the material's own sampling.
+fn sample_light<S: Sky>(
+    scene: &Scene<S>,
+    incoming: &Ray,
+    hit: &HitRecord,
+    rng: &mut dyn RngCore,
+) -> Color {
+    let direction = match scene.lights.sample_direction(hit.point, rng) {
+        Some(direction) => direction.to_unit(),
+        None => return Color::zeros(),
+    };
+    let light_pdf = scene.lights.pdf_value(hit.point, direction);
+    let scattering = hit.material.eval(incoming, hit, direction);
+    if light_pdf <= 0.0 || scattering == Color::zeros() {
+        return Color::zeros();
+    }
+
+    let shadow_ray = Ray::with_time(hit.point, direction, incoming.time);
+    let light_hit = match scene.lights.hit(&shadow_ray, (MIN_HIT_DISTANCE, f64::MAX)) {
+        Some(light_hit) => light_hit,
+        None => return Color::zeros(),
+    };
+    let occlusion_range = (MIN_HIT_DISTANCE, light_hit.t - MIN_HIT_DISTANCE);
+    if scene.world.occluded(&shadow_ray, occlusion_range) {
+        return Color::zeros();
+    }
+
+    let emitted = light_hit.material.emitted(&shadow_ray, &light_hit);
+    let weight = power_heuristic(light_pdf, hit.material.pdf(incoming, hit, direction));
+    weight * scattering * emitted / light_pdf
+}
+
+/// Returns the weight of a sample picked with density `pdf` by one of two sampling strategies,
+/// where the other would have picked it with density `other_pdf`.
+///
+/// Samples the other strategy can't produce keep their full weight.
+fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    if other_pdf <= 0.0 {
+        return 1.0;
+    }
+    pdf.powi(2) / (pdf.powi(2) + other_pdf.powi(2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geom::{Sphere, XzRect};
+    use crate::mat::{DiffuseLight, Lambertian};
+    use crate::random;
+    use crate::scene::SolidSky;
+    use crate::vec3::{Point3, Vec3};
+
+    #[test]
+    fn area_light_is_visible_under_black_sky() {
+        let light = Sphere::new(
+            Point3::zeros(),
+            1.0,
+            DiffuseLight::new(Color::new(4.0, 4.0, 4.0)),
+        );
+        let scene = Scene::new(vec![Box::new(light)], SolidSky::default()).with_max_bounces(1);
+
+        let towards = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let away = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, 1.0));
+
+        let mut rng = random::stream_rng(0, 0);
+        let path_tracer = PathTracer::default();
+        assert_eq!(
+            path_tracer.radiance(&scene, &towards, &mut rng),
+            Color::new(4.0, 4.0, 4.0)
+        );
+        assert_eq!(
+            path_tracer.radiance(&scene, &away, &mut rng),
+            Color::zeros()
+        );
+    }
+
+    #[test]
+    fn light_sampling_agrees_with_plain_path_tracing() {
+        let light = || {
+            XzRect::new(
+                (-0.5, 0.5),
+                (-0.5, 0.5),
+                2.0,
+                DiffuseLight::new(Color::new(10.0, 10.0, 10.0)),
+            )
+        };
+        let floor = XzRect::new((-10.0, 10.0), (-10.0, 10.0), 0.0, Lambertian::default());
+        let scene = Scene::new(
+            vec![Box::new(light()), Box::new(floor)],
+            SolidSky::default(),
+        )
+        .with_lights(vec![Box::new(light())])
+        .with_max_bounces(2);
+        let ray = Ray::new(Point3::new(0.0, 1.0, 1.0), Vec3::new(0.0, -1.0, -1.0));
+
+        let mean = |path_tracer: PathTracer, samples: u64| {
+            let sum: Color = (0..samples)
+                .map(|sample| {
+                    path_tracer.radiance(&scene, &ray, &mut random::stream_rng(1, sample))
+                })
+                .sum();
+            sum / samples as f64
+        };
+        let sampled = mean(PathTracer::default(), 2_000);
+        let plain = mean(PathTracer::default().with_light_sampling(false), 200_000);
+
+        // The irradiance from the light falls off with distance, so the estimates only match if
+        // the light is counted exactly once.
+        assert!(sampled.x > 0.0);
+        assert!(
+            (sampled.x - plain.x).abs() < 0.03 * plain.x,
+            "{} vs {}",
+            sampled,
+            plain
+        );
+    }
+
+    #[test]
+    fn russian_roulette_keeps_the_estimate_unbiased() {
+        // A light inside a closed diffuse room, where light keeps bouncing between the walls.
+        let light = || {
+            Sphere::new(
+                Point3::new(0.0, 2.0, 0.0),
+                0.5,
+                DiffuseLight::new(Color::new(4.0, 4.0, 4.0)),
+            )
+        };
+        let room = Sphere::new(
+            Point3::zeros(),
+            3.0,
+            Lambertian::new(Color::new(0.7, 0.7, 0.7)),
+        );
+        let scene = |min_bounces| {
+            Scene::new(
+                vec![Box::new(light()), Box::new(room.clone())],
+                SolidSky::default(),
+            )
+            .with_lights(vec![Box::new(light())])
+            .with_max_bounces(12)
+            .with_min_bounces(min_bounces)
+        };
+        let ray = Ray::new(Point3::zeros(), Vec3::new(0.0, -1.0, 0.0));
+
+        let mean = |scene: Scene<SolidSky>, samples: u64| {
+            let path_tracer = PathTracer::default();
+            let sum: Color = (0..samples)
+                .map(|sample| {
+                    path_tracer.radiance(&scene, &ray, &mut random::stream_rng(2, sample))
+                })
+                .sum();
+            sum / samples as f64
+        };
+        let full = mean(scene(12), 20_000);
+        let roulette = mean(scene(0), 20_000);
+
+        assert!(full.x > 0.0);
+        assert!(
+            (roulette.x - full.x).abs() < 0.03 * full.x,
+            "{} vs {}",
+            roulette,
+            full
+        );
+    }
+}
@@ -0,0 +1,13 @@
+pub mod aabb;
+pub mod camera;
+pub mod film;
+pub mod geom;
+pub mod integrator;
+pub mod mat;
+pub mod output;
+pub mod random;
+pub mod ray;
+pub mod render;
+pub mod scene;
+pub mod tonemap;
+pub mod vec3;
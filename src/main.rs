@@ -1,95 +1,70 @@
-use std::time::Duration;
+use structopt::StructOpt;
 
-use camera::Camera;
-use geom::{Hittable, Sphere};
-use mat::{Lambertian, NoiseTexture};
-use scene::Scene;
-use vec3::{Color, Point3, Vec3};
+use ray_tracing_in_one_weekend::output::{self, ImageFormat};
+use ray_tracing_in_one_weekend::render;
+use ray_tracing_in_one_weekend::scene::SceneDescription;
 
-mod aabb;
-mod camera;
-mod geom;
-mod mat;
-mod ray;
-mod render;
-mod scene;
-mod vec3;
+use self::cli::Options;
 
-const ASPECT_RATIO: f64 = 16.0 / 9.0;
-const IMAGE_WIDTH: usize = 384;
-const IMAGE_HEIGHT: usize = (IMAGE_WIDTH as f64 / ASPECT_RATIO) as usize;
+mod cli;
 
-fn two_perlin_spheres() -> Vec<Box<dyn Hittable>> {
-    let noise = NoiseTexture::with_scale(3.0);
-    vec![
-        Box::new(Sphere::new(
-            Point3::new(0.0, -1000.0, 0.0),
-            1000.0,
-            Lambertian::new(noise.clone()),
-        )),
-        Box::new(Sphere::new(
-            Point3::new(0.0, 2.0, 0.0),
-            2.0,
-            Lambertian::new(noise),
-        )),
-    ]
-}
+fn main() -> anyhow::Result<()> {
+    let options = Options::from_args();
 
-fn main() {
-    println!("P3\n{} {}\n255", IMAGE_WIDTH, IMAGE_HEIGHT);
+    if let Some(threads) = options.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()?;
+    }
 
-    let scene = Scene {
-        world: two_perlin_spheres(),
-        ..Default::default()
+    let description = match &options.scene_file {
+        Some(path) => SceneDescription::open(path)?,
+        None => SceneDescription::builtin(&options.scene)?,
     };
 
-    let camera = {
-        let up_vec = Vec3::new(0.0, 1.0, 0.0);
-        let look_from = Point3::new(13.0, 2.0, 3.0);
-        let look_at = Point3::zeros();
-        let aperture = 0.0;
-        let focus_dist = 10.0;
-        let shutter_duration = Duration::from_secs(1);
-        Camera::new(
-            up_vec,
-            look_from,
-            look_at,
-            20.0,
-            ASPECT_RATIO,
-            aperture,
-            focus_dist,
-            shutter_duration,
-        )
-    };
+    let (width, height) = options.resolution(&description);
+    let camera_settings = options.camera.apply(description.camera);
+    if let Some(physical) = &camera_settings.physical {
+        physical.validate()?;
+    }
+    let camera = camera_settings.build(width as f64 / height as f64);
 
-    for pixel in render::render(&scene, &camera, IMAGE_WIDTH, IMAGE_HEIGHT) {
-        print_color(pixel, scene.samples_per_pixel);
+    let mut scene = description.scene;
+
+    if let Some(samples) = options.samples_per_pixel {
+        scene = scene.with_samples_per_pixel(samples);
+    }
+    if let Some(depth) = options.max_bounce_depth {
+        scene = scene.with_max_bounces(depth);
+    }
+    if let Some(depth) = options.min_bounce_depth {
+        scene = scene.with_min_bounces(depth);
+    }
+    if let Some(seed) = options.seed {
+        scene = scene.with_seed(seed);
     }
-}
 
-fn print_color(pixel: Color, samples_per_pixel: u32) {
-    let scale = 1.0 / samples_per_pixel as f64;
-    let (r, g, b) = {
-        // Divide the color total by the number of samples and gamma-correct for gamma=2.0.
-        let r = (pixel.x * scale).sqrt();
-        let g = (pixel.y * scale).sqrt();
-        let b = (pixel.z * scale).sqrt();
-        (r, g, b)
-    };
+    let format = ImageFormat::from_path(&options.output)?;
+    let film = render::render(
+        &scene,
+        &camera,
+        &options.integrator,
+        width,
+        height,
+        &options.aovs,
+    );
+    output::save_aovs(&options.output, &film, &options.aovs)?;
 
-    let ir = (256.0 * clamp(r, 0.0, 0.999)) as i64;
-    let ig = (256.0 * clamp(g, 0.0, 0.999)) as i64;
-    let ib = (256.0 * clamp(b, 0.0, 0.999)) as i64;
-    println!("{} {} {}", ir, ig, ib);
-}
+    let mut pixels = film.beauty;
+    let tone_mapper = options.tone_mapper(&pixels, camera_settings.exposure_scale());
 
-fn clamp(mut x: f64, min: f64, max: f64) -> f64 {
-    debug_assert!(min <= max);
-    if x < min {
-        x = min;
-    }
-    if x > max {
-        x = max;
+    // HDR formats keep linear radiance, so only the exposure applies to them.
+    if format.is_hdr() {
+        let scale = tone_mapper.exposure_scale();
+        pixels.iter_mut().for_each(|pixel| *pixel *= scale);
+    } else {
+        tone_mapper.apply_all(&mut pixels);
     }
-    x
+
+    output::save(&options.output, &pixels, width, height)
 }
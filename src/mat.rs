@@ -1,22 +1,118 @@
-pub use self::texture::{CheckeredTexture, NoiseTexture, Texture};
+pub use self::texture::{
+    CheckeredTexture, FilterMode, ImageTexture, NoiseMode, NoiseTexture, Texture, WrapMode,
+};
 
+use std::f64::consts::PI;
 use std::fmt::Debug;
+use std::sync::Arc;
+
+use rand::{Rng, RngCore};
 
 use crate::geom::HitRecord;
 use crate::ray::Ray;
 use crate::vec3::{Color, Vec3};
 
+mod image;
 mod perlin;
 mod texture;
 
 pub trait Material: Debug + Send + Sync {
-    fn scatter(&self, incoming: &Ray, hit: &HitRecord) -> Option<Scatter>;
+    /// Samples an outgoing ray, drawing any random numbers needed from `rng`.
+    fn scatter(&self, incoming: &Ray, hit: &HitRecord, rng: &mut dyn RngCore) -> Option<Scatter>;
+
+    /// Returns the radiance emitted from the surface at `hit`, which is black for most materials.
+    fn emitted(&self, _incoming: &Ray, _hit: &HitRecord) -> Color {
+        Color::zeros()
+    }
+
+    /// Returns the fraction of the light arriving at `hit` from `direction` that is scattered
+    /// back along `incoming`, per unit of solid angle and including the cosine term.
+    ///
+    /// Together with `pdf()`, this lets integrators sample lights directly and weigh those samples
+    /// against scattered rays. Every material that can return a `Scatter` which isn't specular
+    /// must implement both, such that `attenuation == eval() / pdf()` for the scattered direction.
+    fn eval(&self, _incoming: &Ray, _hit: &HitRecord, _direction: Vec3) -> Color {
+        Color::zeros()
+    }
+
+    /// Returns the probability density, per unit of solid angle, with which `scatter()` picks
+    /// `direction`, which is zero for specular materials.
+    fn pdf(&self, _incoming: &Ray, _hit: &HitRecord, _direction: Vec3) -> f64 {
+        0.0
+    }
+
+    /// Returns the color of the surface at `hit` regardless of lighting, as used for albedo
+    /// buffers. Lights return their emission, and materials without a color return black.
+    fn albedo(&self, _hit: &HitRecord) -> Color {
+        Color::zeros()
+    }
+
+    /// Returns an address identifying this material, which stays the same when it is shared
+    /// between objects through an `Arc`.
+    fn instance_id(&self) -> usize {
+        self as *const Self as *const () as usize
+    }
+}
+
+/// Allows a single material to be shared between several objects.
+impl<M: Material + ?Sized> Material for Arc<M> {
+    fn scatter(&self, incoming: &Ray, hit: &HitRecord, rng: &mut dyn RngCore) -> Option<Scatter> {
+        (**self).scatter(incoming, hit, rng)
+    }
+
+    fn emitted(&self, incoming: &Ray, hit: &HitRecord) -> Color {
+        (**self).emitted(incoming, hit)
+    }
+
+    fn eval(&self, incoming: &Ray, hit: &HitRecord, direction: Vec3) -> Color {
+        (**self).eval(incoming, hit, direction)
+    }
+
+    fn pdf(&self, incoming: &Ray, hit: &HitRecord, direction: Vec3) -> f64 {
+        (**self).pdf(incoming, hit, direction)
+    }
+
+    fn albedo(&self, hit: &HitRecord) -> Color {
+        (**self).albedo(hit)
+    }
+
+    fn instance_id(&self) -> usize {
+        (**self).instance_id()
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Scatter {
     pub ray: Ray,
+    /// The material's `eval()` divided by `pdf`, i.e. the factor by which the light arriving
+    /// along `ray` contributes to the light leaving the surface.
     pub attenuation: Color,
+    /// The probability density with which the direction of `ray` was picked, per unit of solid
+    /// angle, or zero if `is_specular`.
+    pub pdf: f64,
+    /// Whether `ray` was picked from a few discrete directions (a delta distribution), such as a
+    /// perfect reflection, so that light sampling can't contribute to it.
+    pub is_specular: bool,
+}
+
+impl Scatter {
+    pub fn new(ray: Ray, attenuation: Color, pdf: f64) -> Self {
+        Scatter {
+            ray,
+            attenuation,
+            pdf,
+            is_specular: false,
+        }
+    }
+
+    pub fn specular(ray: Ray, attenuation: Color) -> Self {
+        Scatter {
+            ray,
+            attenuation,
+            pdf: 0.0,
+            is_specular: true,
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -37,12 +133,30 @@ impl Default for Lambertian<Color> {
 }
 
 impl<T: Texture> Material for Lambertian<T> {
-    fn scatter(&self, incoming: &Ray, hit: &HitRecord) -> Option<Scatter> {
-        let scatter_direction = hit.normal + Vec3::random_unit();
-        Some(Scatter {
-            ray: Ray::with_time(hit.point, scatter_direction, incoming.time),
-            attenuation: self.albedo.value(hit.texture_u, hit.texture_v, hit.point),
-        })
+    fn scatter(&self, incoming: &Ray, hit: &HitRecord, rng: &mut dyn RngCore) -> Option<Scatter> {
+        // Offsetting the normal by a random unit vector gives cosine-weighted directions.
+        let mut scatter_direction = hit.normal + Vec3::random_unit(rng);
+        if scatter_direction.len_squared() < 1e-16 {
+            scatter_direction = hit.normal;
+        }
+        Some(Scatter::new(
+            Ray::with_time(hit.point, scatter_direction, incoming.time),
+            self.albedo.value(hit.texture_u, hit.texture_v, hit.point),
+            self.pdf(incoming, hit, scatter_direction),
+        ))
+    }
+
+    fn eval(&self, incoming: &Ray, hit: &HitRecord, direction: Vec3) -> Color {
+        let albedo = self.albedo.value(hit.texture_u, hit.texture_v, hit.point);
+        albedo * self.pdf(incoming, hit, direction)
+    }
+
+    fn pdf(&self, _: &Ray, hit: &HitRecord, direction: Vec3) -> f64 {
+        hit.normal.dot(direction.to_unit()).max(0.0) / PI
+    }
+
+    fn albedo(&self, hit: &HitRecord) -> Color {
+        self.albedo.value(hit.texture_u, hit.texture_v, hit.point)
     }
 }
 
@@ -65,12 +179,32 @@ impl Default for SimpleDiffuse<Color> {
 }
 
 impl<T: Texture> Material for SimpleDiffuse<T> {
-    fn scatter(&self, incoming: &Ray, hit: &HitRecord) -> Option<Scatter> {
-        let scatter_direction = Vec3::random_in_hemisphere(hit.normal);
-        Some(Scatter {
-            ray: Ray::with_time(hit.point, scatter_direction, incoming.time),
-            attenuation: self.albedo.value(hit.texture_u, hit.texture_v, hit.point),
-        })
+    fn scatter(&self, incoming: &Ray, hit: &HitRecord, rng: &mut dyn RngCore) -> Option<Scatter> {
+        let scatter_direction = Vec3::random_in_hemisphere(rng, hit.normal);
+        Some(Scatter::new(
+            Ray::with_time(hit.point, scatter_direction, incoming.time),
+            self.albedo.value(hit.texture_u, hit.texture_v, hit.point),
+            1.0 / (2.0 * PI),
+        ))
+    }
+
+    /// Scattering uniformly over the hemisphere with the albedo as attenuation amounts to a
+    /// constant value over the hemisphere, rather than one proportional to the cosine.
+    fn eval(&self, incoming: &Ray, hit: &HitRecord, direction: Vec3) -> Color {
+        let albedo = self.albedo.value(hit.texture_u, hit.texture_v, hit.point);
+        albedo * self.pdf(incoming, hit, direction)
+    }
+
+    fn pdf(&self, _: &Ray, hit: &HitRecord, direction: Vec3) -> f64 {
+        if hit.normal.dot(direction) > 0.0 {
+            1.0 / (2.0 * PI)
+        } else {
+            0.0
+        }
+    }
+
+    fn albedo(&self, hit: &HitRecord) -> Color {
+        self.albedo.value(hit.texture_u, hit.texture_v, hit.point)
     }
 }
 
@@ -96,22 +230,57 @@ impl Default for Metallic {
 }
 
 impl Material for Metallic {
-    fn scatter(&self, incoming: &Ray, hit: &HitRecord) -> Option<Scatter> {
+    fn scatter(&self, incoming: &Ray, hit: &HitRecord, rng: &mut dyn RngCore) -> Option<Scatter> {
         let reflected = incoming.direction.to_unit().reflect(hit.normal);
+        if self.fuzz <= 0.0 {
+            let scattered = Ray::with_time(hit.point, reflected, incoming.time);
+            return Some(Scatter::specular(scattered, self.albedo));
+        }
+
         let scattered = Ray::with_time(
             hit.point,
-            reflected + self.fuzz * Vec3::random_in_unit_sphere(),
+            reflected + self.fuzz * Vec3::random_in_unit_sphere(rng),
             incoming.time,
         );
         if scattered.direction.dot(hit.normal) > 0.0 {
-            Some(Scatter {
-                ray: scattered,
-                attenuation: self.albedo,
-            })
+            let pdf = self.pdf(incoming, hit, scattered.direction);
+            Some(Scatter::new(scattered, self.albedo, pdf))
         } else {
             None
         }
     }
+
+    fn eval(&self, incoming: &Ray, hit: &HitRecord, direction: Vec3) -> Color {
+        self.albedo * self.pdf(incoming, hit, direction)
+    }
+
+    /// Fuzzy reflections offset the mirrored direction `r` by a point uniformly distributed in a
+    /// ball of radius `fuzz`, so the density of a direction is the ball's volume along that ray,
+    /// weighted by the squared distance from the origin as the solid angle grows with it.
+    fn pdf(&self, incoming: &Ray, hit: &HitRecord, direction: Vec3) -> f64 {
+        if self.fuzz <= 0.0 || direction.dot(hit.normal) <= 0.0 {
+            return 0.0;
+        }
+
+        let reflected = incoming.direction.to_unit().reflect(hit.normal);
+        // The ray `t * direction` enters and leaves the ball where `|t * direction - r| = fuzz`.
+        let b = direction.to_unit().dot(reflected);
+        let discriminant = b.powi(2) - (1.0 - self.fuzz.powi(2));
+        if discriminant <= 0.0 {
+            return 0.0;
+        }
+
+        let t_exit = b + discriminant.sqrt();
+        let t_enter = (b - discriminant.sqrt()).max(0.0);
+        if t_exit <= 0.0 {
+            return 0.0;
+        }
+        (t_exit.powi(3) - t_enter.powi(3)) / (4.0 * PI * self.fuzz.powi(3))
+    }
+
+    fn albedo(&self, _: &HitRecord) -> Color {
+        self.albedo
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -126,7 +295,7 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, incoming: &Ray, hit: &HitRecord) -> Option<Scatter> {
+    fn scatter(&self, incoming: &Ray, hit: &HitRecord, rng: &mut dyn RngCore) -> Option<Scatter> {
         let etai_over_etat = if hit.is_front_face {
             1.0 / self.refraction_index
         } else {
@@ -138,10 +307,8 @@ impl Material for Dielectric {
             let cos_theta = (-unit_direction).dot(hit.normal).min(1.0);
             let sin_theta = (1.0 - cos_theta.powi(2)).sqrt();
 
-            if etai_over_etat * sin_theta > 1.0 {
-                let reflected = unit_direction.reflect(hit.normal);
-                Ray::with_time(hit.point, reflected, incoming.time)
-            } else if rand::random::<f64>() < schlick(cos_theta, etai_over_etat) {
+            let cannot_refract = etai_over_etat * sin_theta > 1.0;
+            if cannot_refract || rng.gen::<f64>() < schlick(cos_theta, etai_over_etat) {
                 let reflected = unit_direction.reflect(hit.normal);
                 Ray::with_time(hit.point, reflected, incoming.time)
             } else {
@@ -150,10 +317,72 @@ impl Material for Dielectric {
             }
         };
 
-        Some(Scatter {
-            ray: scattered,
-            attenuation: Color::ones(),
-        })
+        Some(Scatter::specular(scattered, Color::ones()))
+    }
+
+    fn albedo(&self, _: &HitRecord) -> Color {
+        Color::ones()
+    }
+}
+
+/// Scatters uniformly in all directions, acting as the phase function of participating media.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Isotropic<T: Texture> {
+    pub albedo: T,
+}
+
+impl<T: Texture> Isotropic<T> {
+    pub fn new(albedo: T) -> Self {
+        Isotropic { albedo }
+    }
+}
+
+impl<T: Texture> Material for Isotropic<T> {
+    fn scatter(&self, incoming: &Ray, hit: &HitRecord, rng: &mut dyn RngCore) -> Option<Scatter> {
+        Some(Scatter::new(
+            Ray::with_time(hit.point, Vec3::random_unit(rng), incoming.time),
+            self.albedo.value(hit.texture_u, hit.texture_v, hit.point),
+            1.0 / (4.0 * PI),
+        ))
+    }
+
+    fn eval(&self, incoming: &Ray, hit: &HitRecord, direction: Vec3) -> Color {
+        let albedo = self.albedo.value(hit.texture_u, hit.texture_v, hit.point);
+        albedo * self.pdf(incoming, hit, direction)
+    }
+
+    fn pdf(&self, _: &Ray, _: &HitRecord, _: Vec3) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+
+    fn albedo(&self, hit: &HitRecord) -> Color {
+        self.albedo.value(hit.texture_u, hit.texture_v, hit.point)
+    }
+}
+
+/// An area light which emits light evenly in all directions and never scatters.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiffuseLight<T: Texture> {
+    pub emit: T,
+}
+
+impl<T: Texture> DiffuseLight<T> {
+    pub fn new(emit: T) -> Self {
+        DiffuseLight { emit }
+    }
+}
+
+impl<T: Texture> Material for DiffuseLight<T> {
+    fn scatter(&self, _: &Ray, _: &HitRecord, _: &mut dyn RngCore) -> Option<Scatter> {
+        None
+    }
+
+    fn emitted(&self, _: &Ray, hit: &HitRecord) -> Color {
+        self.emit.value(hit.texture_u, hit.texture_v, hit.point)
+    }
+
+    fn albedo(&self, hit: &HitRecord) -> Color {
+        self.emit.value(hit.texture_u, hit.texture_v, hit.point)
     }
 }
 
@@ -162,3 +391,78 @@ fn schlick(cosine: f64, refraction_index: f64) -> f64 {
     let r0_squared = r0.powi(2);
     r0_squared + (1.0 - r0_squared) * (1.0 - cosine).powi(5)
 }
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use super::*;
+    use crate::random;
+    use crate::vec3::Point3;
+
+    /// Integrates `material.pdf()` over the sphere of directions leaving a floor hit from above.
+    fn integrate_pdf<M: Material>(material: &M, incoming: Vec3) -> f64 {
+        let ray = Ray::new(Point3::new(0.0, 1.0, 0.0), incoming);
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        let hit = HitRecord::new(Point3::zeros(), normal, material, 0.0, 0.0, 1.0, true);
+
+        let mut rng = random::stream_rng(0, 0);
+        let samples = 200_000;
+        let sum: f64 = (0..samples)
+            .map(|_| material.pdf(&ray, &hit, Vec3::random_unit(&mut rng)))
+            .sum();
+        // Uniform directions have density `1 / 4π`.
+        4.0 * PI * sum / samples as f64
+    }
+
+    #[test]
+    fn pdfs_integrate_to_one() {
+        let down = Vec3::new(0.0, -1.0, 0.0);
+        let grazing = Vec3::new(1.0, -0.3, 0.0);
+        assert_float_eq!(
+            integrate_pdf(&Lambertian::default(), down),
+            1.0,
+            abs <= 0.01
+        );
+        assert_float_eq!(
+            integrate_pdf(&Isotropic::new(Color::ones()), down),
+            1.0,
+            abs <= 0.01
+        );
+        let metal = Metallic::new(Color::ones(), 0.5);
+        assert_float_eq!(integrate_pdf(&metal, down), 1.0, abs <= 0.02);
+        // Near grazing angles, part of the fuzz ball lies below the surface and gets absorbed.
+        assert!(integrate_pdf(&metal, grazing) < 0.95);
+    }
+
+    #[test]
+    fn specular_materials_have_no_density() {
+        let ray = Ray::new(Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let mut rng = random::stream_rng(0, 0);
+        let materials: [&dyn Material; 2] = [&Metallic::default(), &Dielectric::new(1.5)];
+        for &material in &materials {
+            let normal = Vec3::new(0.0, 1.0, 0.0);
+            let hit = HitRecord::new(Point3::zeros(), normal, material, 0.0, 0.0, 1.0, true);
+            let scatter = material.scatter(&ray, &hit, &mut rng).unwrap();
+            assert!(scatter.is_specular);
+            assert_eq!(material.pdf(&ray, &hit, scatter.ray.direction), 0.0);
+        }
+    }
+
+    #[test]
+    fn lambertian_eval_matches_cosine_density() {
+        let material = Lambertian::new(Color::new(0.5, 0.5, 0.5));
+        let ray = Ray::new(Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        let hit = HitRecord::new(Point3::zeros(), normal, &material, 0.0, 0.0, 1.0, true);
+
+        let direction = Vec3::new(1.0, 1.0, 0.0).to_unit();
+        let pdf = material.pdf(&ray, &hit, direction);
+        assert_float_eq!(pdf, direction.y / PI, abs <= 1e-12);
+        assert_eq!(
+            material.eval(&ray, &hit, direction),
+            0.5 * pdf * Color::ones()
+        );
+        assert_eq!(material.pdf(&ray, &hit, -direction), 0.0);
+    }
+}
@@ -0,0 +1,231 @@
+//! Decoders for the image formats accepted by `ImageTexture`.
+
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use anyhow::{anyhow, format_err, Context};
+
+use crate::vec3::Color;
+
+/// A decoded image whose channels are still sRGB-encoded, normalized to `0.0..=1.0`.
+#[derive(Clone, Debug, PartialEq)]
+pub(super) struct RawImage {
+    pub width: usize,
+    pub height: usize,
+    /// Pixels in row-major order, starting from the top-left corner.
+    pub pixels: Vec<Color>,
+}
+
+/// Loads a PPM (`P3` or `P6`) or PNG image, detecting the format from its contents.
+pub(super) fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<RawImage> {
+    let path = path.as_ref();
+    let mut bytes = Vec::new();
+    File::open(path)
+        .and_then(|file| BufReader::new(file).read_to_end(&mut bytes))
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    decode(&bytes).with_context(|| format!("Failed to decode {}", path.display()))
+}
+
+pub(super) fn decode(bytes: &[u8]) -> anyhow::Result<RawImage> {
+    match bytes {
+        [b'P', b'3', ..] | [b'P', b'6', ..] => decode_ppm(bytes),
+        [0x89, b'P', b'N', b'G', ..] => decode_png(bytes),
+        _ => Err(anyhow!("Unrecognized image format, expected PPM or PNG")),
+    }
+}
+
+fn decode_png(bytes: &[u8]) -> anyhow::Result<RawImage> {
+    // The default transformations expand palettes and low bit depths and strip 16-bit channels,
+    // so the output is always 8 bits per channel.
+    let (info, mut reader) = png::Decoder::new(bytes).read_info()?;
+    let mut buffer = vec![0; info.buffer_size()];
+    reader.next_frame(&mut buffer)?;
+
+    let channels = match info.color_type {
+        png::ColorType::Grayscale => 1,
+        png::ColorType::GrayscaleAlpha => 2,
+        png::ColorType::RGB => 3,
+        png::ColorType::RGBA => 4,
+        png::ColorType::Indexed => return Err(anyhow!("Indexed PNG was not expanded")),
+    };
+
+    let (width, height) = (info.width as usize, info.height as usize);
+    let pixels = buffer
+        .chunks(info.line_size)
+        .take(height)
+        .flat_map(|row| row.chunks(channels).take(width))
+        .map(|texel| {
+            let channel = |i: usize| f64::from(texel[i]) / 255.0;
+            if channels < 3 {
+                Color::ones() * channel(0)
+            } else {
+                Color::new(channel(0), channel(1), channel(2))
+            }
+        })
+        .collect();
+
+    Ok(RawImage {
+        width,
+        height,
+        pixels,
+    })
+}
+
+/// Returns the next whitespace-delimited header token, skipping any `#` comments.
+fn next_token<'a>(bytes: &'a [u8], cursor: &mut usize) -> anyhow::Result<&'a [u8]> {
+    loop {
+        match bytes.get(*cursor) {
+            Some(b'#') => {
+                while matches!(bytes.get(*cursor), Some(&b) if b != b'\n') {
+                    *cursor += 1;
+                }
+            }
+            Some(b) if b.is_ascii_whitespace() => *cursor += 1,
+            Some(_) => break,
+            None => return Err(anyhow!("Unexpected end of PPM data")),
+        }
+    }
+
+    let start = *cursor;
+    while matches!(bytes.get(*cursor), Some(b) if !b.is_ascii_whitespace()) {
+        *cursor += 1;
+    }
+    Ok(&bytes[start..*cursor])
+}
+
+fn next_number(bytes: &[u8], cursor: &mut usize) -> anyhow::Result<usize> {
+    let token = next_token(bytes, cursor)?;
+    std::str::from_utf8(token)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| format_err!("Invalid PPM value `{}`", String::from_utf8_lossy(token)))
+}
+
+fn decode_ppm(bytes: &[u8]) -> anyhow::Result<RawImage> {
+    let mut cursor = 2;
+    let is_binary = bytes.starts_with(b"P6");
+    let width = next_number(bytes, &mut cursor)?;
+    let height = next_number(bytes, &mut cursor)?;
+    let max_value = next_number(bytes, &mut cursor)?;
+    if max_value == 0 || max_value > 65535 {
+        return Err(format_err!("Invalid PPM maximum value {}", max_value));
+    }
+
+    let too_large = || format_err!("PPM image of {}x{} pixels is too large", width, height);
+    let sample_count = width
+        .checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(3))
+        .ok_or_else(too_large)?;
+    let samples: Vec<usize> = if is_binary {
+        // Exactly one whitespace byte separates the header from the raster.
+        let start = cursor + 1;
+        let bytes_per_sample = if max_value < 256 { 1 } else { 2 };
+        let end = sample_count
+            .checked_mul(bytes_per_sample)
+            .and_then(|len| len.checked_add(start))
+            .ok_or_else(too_large)?;
+        let raster = bytes
+            .get(start..end)
+            .ok_or_else(|| anyhow!("PPM raster is truncated"))?;
+
+        if bytes_per_sample == 1 {
+            raster.iter().map(|&b| usize::from(b)).collect()
+        } else {
+            raster
+                .chunks(2)
+                .map(|pair| usize::from(pair[0]) << 8 | usize::from(pair[1]))
+                .collect()
+        }
+    } else {
+        (0..sample_count)
+            .map(|_| next_number(bytes, &mut cursor))
+            .collect::<anyhow::Result<_>>()?
+    };
+
+    let scale = 1.0 / max_value as f64;
+    let pixels = samples
+        .chunks(3)
+        .map(|rgb| Color::new(rgb[0] as f64, rgb[1] as f64, rgb[2] as f64) * scale)
+        .collect();
+
+    Ok(RawImage {
+        width,
+        height,
+        pixels,
+    })
+}
+
+/// Converts a single sRGB-encoded channel value to linear light.
+#[inline]
+pub fn srgb_to_linear(value: f64) -> f64 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_ascii_and_binary_ppm() {
+        let ascii = b"P3\n# comment\n2 1\n255\n255 0 0  0 0 255\n";
+        let binary = [&b"P6 2 1 255\n"[..], &[255, 0, 0, 0, 0, 255]].concat();
+
+        for bytes in &[&ascii[..], &binary[..]] {
+            let image = decode(bytes).unwrap();
+            assert_eq!((image.width, image.height), (2, 1));
+            assert_eq!(
+                image.pixels,
+                [Color::new(1.0, 0.0, 0.0), Color::new(0.0, 0.0, 1.0)]
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_ppm() {
+        let bytes = b"P6 2 2 255\n\xff\xff\xff";
+        assert!(decode(bytes).is_err());
+    }
+
+    #[test]
+    fn rejects_oversized_ppm() {
+        let huge = format!("P6 {0} {0} 65535\n", usize::MAX / 2);
+        let error = decode(huge.as_bytes()).unwrap_err().to_string();
+        assert!(error.contains("too large"), "{}", error);
+
+        // Small enough to count the samples, but not their bytes.
+        let wide = format!("P6 {} 1 65535\n", usize::MAX / 4);
+        assert!(decode(wide.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn decodes_png() {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut bytes, 2, 1);
+            encoder.set_color(png::ColorType::RGB);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&[0, 255, 0, 51, 51, 51]).unwrap();
+        }
+
+        let image = decode(&bytes).unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(
+            image.pixels,
+            [Color::new(0.0, 1.0, 0.0), Color::new(0.2, 0.2, 0.2)]
+        );
+    }
+
+    #[test]
+    fn srgb_transfer_endpoints() {
+        assert_eq!(srgb_to_linear(0.0), 0.0);
+        assert!((srgb_to_linear(1.0) - 1.0).abs() < 1e-12);
+        assert!((srgb_to_linear(0.5) - 0.214_041).abs() < 1e-6);
+    }
+}
@@ -1,12 +1,13 @@
-use rand::Rng;
+use rand::{Rng, SeedableRng};
 
-use crate::vec3::Point3;
+use crate::random::SampleRng;
+use crate::vec3::{Point3, Vec3};
 
 const POINT_COUNT: usize = 256;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Perlin {
-    random_floats: Box<[f64]>,
+    random_gradients: Box<[Vec3]>,
     perm_x: Box<[usize]>,
     perm_y: Box<[usize]>,
     perm_z: Box<[usize]>,
@@ -14,48 +15,74 @@ pub struct Perlin {
 
 impl Perlin {
     pub fn new() -> Self {
-        Perlin::with_rng(rand::thread_rng())
+        Perlin::with_seed(0)
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        Perlin::with_rng(SampleRng::seed_from_u64(seed))
     }
 
     pub fn with_rng<R: Rng>(mut rng: R) -> Self {
-        let random_floats = (&mut rng)
-            .sample_iter(rand::distributions::Standard)
-            .take(POINT_COUNT)
+        let random_gradients = (0..POINT_COUNT)
+            .map(|_| {
+                Vec3::new(
+                    rng.gen_range(-1.0, 1.0),
+                    rng.gen_range(-1.0, 1.0),
+                    rng.gen_range(-1.0, 1.0),
+                )
+                .to_unit()
+            })
             .collect();
 
         Perlin {
-            random_floats,
+            random_gradients,
             perm_x: gen_perlin_permutation(&mut rng),
             perm_y: gen_perlin_permutation(&mut rng),
             perm_z: gen_perlin_permutation(&mut rng),
         }
     }
 
+    /// Returns gradient noise at `point`, in the range `-1.0..=1.0`.
     pub fn noise_at(&self, point: Point3) -> f64 {
-        let mut u = point.x - point.x.floor();
-        let mut v = point.y - point.y.floor();
-        let mut w = point.z - point.z.floor();
-        u = u.powi(2) * (3.0 - 2.0 * u);
-        v = v.powi(2) * (3.0 - 2.0 * v);
-        w = w.powi(2) * (3.0 - 2.0 * w);
-
-        let i = point.x.floor();
-        let j = point.y.floor();
-        let k = point.z.floor();
-
-        let mut c = [[[0.0; 2]; 2]; 2];
-
-        for di in 0..2 {
-            for dj in 0..2 {
-                for dk in 0..2 {
-                    c[di][dj][dk] = self.random_floats[self.perm_x[(i as usize + di) & 255]
-                        ^ self.perm_y[(j as usize + dj) & 255]
-                        ^ self.perm_z[(k as usize + dk) & 255]];
+        let u = point.x - point.x.floor();
+        let v = point.y - point.y.floor();
+        let w = point.z - point.z.floor();
+
+        // Wrapping to the lattice size here keeps negative coordinates from all mapping to zero.
+        let lattice = |x: f64| (x.floor() as i64).rem_euclid(POINT_COUNT as i64) as usize;
+        let (i, j, k) = (lattice(point.x), lattice(point.y), lattice(point.z));
+
+        let mut c = [[[Vec3::zeros(); 2]; 2]; 2];
+
+        for (di, plane) in c.iter_mut().enumerate() {
+            for (dj, row) in plane.iter_mut().enumerate() {
+                for (dk, cell) in row.iter_mut().enumerate() {
+                    *cell = self.random_gradients[self.perm_x[(i + di) & 255]
+                        ^ self.perm_y[(j + dj) & 255]
+                        ^ self.perm_z[(k + dk) & 255]];
                 }
             }
         }
 
-        trilinear_interp(c, u, v, w)
+        perlin_interp(c, u, v, w)
+    }
+
+    /// Sums `depth` octaves of noise, each at double the frequency and half the weight, and
+    /// returns the magnitude of the sum.
+    ///
+    /// This is non-negative but not bounded by one, since the weights add up to almost two.
+    pub fn turbulence(&self, point: Point3, depth: u32) -> f64 {
+        let mut accum = 0.0;
+        let mut point = point;
+        let mut weight = 1.0;
+
+        for _ in 0..depth {
+            accum += weight * self.noise_at(point);
+            weight *= 0.5;
+            point *= 2.0;
+        }
+
+        accum.abs()
     }
 }
 
@@ -71,19 +98,69 @@ fn gen_perlin_permutation<R: Rng>(mut rng: R) -> Box<[usize]> {
 }
 
 #[inline]
-fn trilinear_interp(interp_point: [[[f64; 2]; 2]; 2], u: f64, v: f64, w: f64) -> f64 {
+fn perlin_interp(gradients: [[[Vec3; 2]; 2]; 2], u: f64, v: f64, w: f64) -> f64 {
+    // Hermite smoothing hides the lattice's grid structure.
+    let uu = u.powi(2) * (3.0 - 2.0 * u);
+    let vv = v.powi(2) * (3.0 - 2.0 * v);
+    let ww = w.powi(2) * (3.0 - 2.0 * w);
     let mut accum = 0.0;
 
-    for i in 0..2 {
-        for j in 0..2 {
-            for k in 0..2 {
-                accum += (i as f64 * u + (1.0 - i as f64) * (1.0 - u))
-                    * (j as f64 * v + (1.0 - j as f64) * (1.0 - v))
-                    * (k as f64 * w + (1.0 - k as f64) * (1.0 - w))
-                    * interp_point[i][j][k];
+    for (i, plane) in gradients.iter().enumerate() {
+        for (j, row) in plane.iter().enumerate() {
+            for (k, gradient) in row.iter().enumerate() {
+                let (fi, fj, fk) = (i as f64, j as f64, k as f64);
+                let weight = Vec3::new(u - fi, v - fj, w - fk);
+                accum += (fi * uu + (1.0 - fi) * (1.0 - uu))
+                    * (fj * vv + (1.0 - fj) * (1.0 - vv))
+                    * (fk * ww + (1.0 - fk) * (1.0 - ww))
+                    * gradient.dot(weight);
             }
         }
     }
 
     accum
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn perlin() -> Perlin {
+        Perlin::with_seed(42)
+    }
+
+    #[test]
+    fn noise_vanishes_on_lattice_points() {
+        let perlin = perlin();
+        for &p in &[Point3::zeros(), Point3::new(3.0, -7.0, 12.0)] {
+            assert!(perlin.noise_at(p).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn negative_coordinates_are_not_mirrored() {
+        let perlin = perlin();
+        let samples: Vec<f64> = (1..20)
+            .map(|i| perlin.noise_at(Point3::new(-0.37 * i as f64, -1.5, -2.25)))
+            .collect();
+
+        assert!(samples.iter().all(|n| (-1.0..=1.0).contains(n)));
+        // Saturating casts would put every negative lattice cell at index zero.
+        let distinct = samples.windows(2).filter(|w| w[0] != w[1]).count();
+        assert!(distinct > samples.len() / 2);
+
+        let period = POINT_COUNT as f64;
+        let point = Point3::new(-0.3, -0.6, -0.9);
+        let shifted = point + Vec3::new(period, period, period);
+        assert!((perlin.noise_at(point) - perlin.noise_at(shifted)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn turbulence_is_non_negative() {
+        let perlin = perlin();
+        for i in 0..100 {
+            let p = Point3::new(0.13 * i as f64, -0.29 * i as f64, 0.71 * i as f64);
+            assert!(perlin.turbulence(p, 7) >= 0.0);
+        }
+    }
+}
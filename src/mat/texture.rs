@@ -1,5 +1,10 @@
 use std::fmt::Debug;
+use std::path::Path;
+use std::sync::Arc;
 
+use anyhow::format_err;
+
+use super::image::{self, srgb_to_linear};
 use super::perlin::Perlin;
 use crate::vec3::{Color, Point3};
 
@@ -7,6 +12,12 @@ pub trait Texture: Debug + Send + Sync {
     fn value(&self, u: f64, v: f64, point: Point3) -> Color;
 }
 
+impl<T: Texture + ?Sized> Texture for Arc<T> {
+    fn value(&self, u: f64, v: f64, point: Point3) -> Color {
+        (**self).value(u, v, point)
+    }
+}
+
 impl Texture for Color {
     fn value(&self, _: f64, _: f64, _: Point3) -> Color {
         *self
@@ -36,10 +47,27 @@ impl<T: Texture, U: Texture> Texture for CheckeredTexture<T, U> {
     }
 }
 
+/// Octaves summed by the turbulent `NoiseTexture` modes.
+const TURBULENCE_DEPTH: u32 = 7;
+
+/// How a `NoiseTexture` turns Perlin noise into a color.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum NoiseMode {
+    /// Smooth noise, remapped to `0.0..=1.0`.
+    #[default]
+    Plain,
+    /// Summed octaves of noise, giving a camouflage-like pattern. The sum can exceed one, so it
+    /// is clamped.
+    Turbulence,
+    /// Sine stripes along `z`, with their phase perturbed by turbulence.
+    Marble,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct NoiseTexture {
     noise: Perlin,
     scale: f64,
+    mode: NoiseMode,
 }
 
 impl NoiseTexture {
@@ -51,12 +79,255 @@ impl NoiseTexture {
         NoiseTexture {
             noise: Perlin::new(),
             scale,
+            mode: NoiseMode::default(),
         }
     }
+
+    /// Regenerates the noise from `seed`, so that textures built with different seeds differ.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.noise = Perlin::with_seed(seed);
+        self
+    }
+
+    pub fn with_mode(mut self, mode: NoiseMode) -> Self {
+        self.mode = mode;
+        self
+    }
+}
+
+impl Default for NoiseTexture {
+    fn default() -> Self {
+        NoiseTexture::new()
+    }
 }
 
 impl Texture for NoiseTexture {
     fn value(&self, _: f64, _: f64, point: Point3) -> Color {
-        Color::ones() * self.noise.noise_at(self.scale * point)
+        let intensity = match self.mode {
+            NoiseMode::Plain => 0.5 * (1.0 + self.noise.noise_at(self.scale * point)),
+            NoiseMode::Turbulence => self
+                .noise
+                .turbulence(self.scale * point, TURBULENCE_DEPTH)
+                .min(1.0),
+            NoiseMode::Marble => {
+                let turbulence = self.noise.turbulence(point, TURBULENCE_DEPTH);
+                0.5 * (1.0 + (self.scale * point.z + 10.0 * turbulence).sin())
+            }
+        };
+
+        Color::ones() * intensity
+    }
+}
+
+/// How an `ImageTexture` reconstructs colors between texel centers.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum FilterMode {
+    Nearest,
+    #[default]
+    Bilinear,
+}
+
+/// How an `ImageTexture` handles texture coordinates outside of `0.0..=1.0`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum WrapMode {
+    /// Tiles the image.
+    #[default]
+    Repeat,
+    /// Extends the edge texels.
+    Clamp,
+}
+
+/// A texture mapped from an image by its `(u, v)` coordinates, with `v` increasing upwards.
+///
+/// Texels are stored in linear color; clones share the same pixel data.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    pixels: Arc<[Color]>,
+    filter: FilterMode,
+    wrap: WrapMode,
+}
+
+impl ImageTexture {
+    /// Creates a texture from linear `pixels` in row-major order, starting from the top-left.
+    pub fn new(width: usize, height: usize, pixels: Vec<Color>) -> anyhow::Result<Self> {
+        if width == 0 || height == 0 || pixels.len() != width * height {
+            return Err(format_err!(
+                "Expected {}x{} pixels, found {}",
+                width,
+                height,
+                pixels.len()
+            ));
+        }
+
+        Ok(ImageTexture {
+            width,
+            height,
+            pixels: pixels.into(),
+            filter: FilterMode::default(),
+            wrap: WrapMode::default(),
+        })
+    }
+
+    /// Loads a PPM (`P3` or `P6`) or PNG file, whose colors are assumed to be sRGB-encoded.
+    pub fn open<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let raw = image::load(path)?;
+        let pixels = raw
+            .pixels
+            .into_iter()
+            .map(|c| {
+                Color::new(
+                    srgb_to_linear(c.x),
+                    srgb_to_linear(c.y),
+                    srgb_to_linear(c.z),
+                )
+            })
+            .collect();
+
+        ImageTexture::new(raw.width, raw.height, pixels)
+    }
+
+    pub fn with_filter(mut self, filter: FilterMode) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn with_wrap(mut self, wrap: WrapMode) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    fn texel(&self, x: isize, y: isize) -> Color {
+        let address = |i: isize, size: usize| match self.wrap {
+            WrapMode::Repeat => i.rem_euclid(size as isize) as usize,
+            WrapMode::Clamp => i.max(0).min(size as isize - 1) as usize,
+        };
+        self.pixels[address(y, self.height) * self.width + address(x, self.width)]
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _: Point3) -> Color {
+        // Bring the coordinates into `0.0..=1.0` first, so that huge or non-finite ones can't
+        // overflow the texel indices. Wrapping them here gives the same texels as wrapping the
+        // indices would.
+        let wrap = |coord: f64| match self.wrap {
+            _ if !coord.is_finite() => 0.0,
+            WrapMode::Repeat => coord.rem_euclid(1.0),
+            WrapMode::Clamp => coord.clamp(0.0, 1.0),
+        };
+
+        // Image rows start at the top, whereas `v` increases upwards.
+        let s = wrap(u) * self.width as f64;
+        let t = (1.0 - wrap(v)) * self.height as f64;
+
+        match self.filter {
+            FilterMode::Nearest => self.texel(s.floor() as isize, t.floor() as isize),
+            FilterMode::Bilinear => {
+                let (s, t) = (s - 0.5, t - 0.5);
+                let (x, y) = (s.floor(), t.floor());
+                let (fx, fy) = (s - x, t - y);
+                let (x, y) = (x as isize, y as isize);
+
+                let top = (1.0 - fx) * self.texel(x, y) + fx * self.texel(x + 1, y);
+                let bottom = (1.0 - fx) * self.texel(x, y + 1) + fx * self.texel(x + 1, y + 1);
+                (1.0 - fy) * top + fy * bottom
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use super::*;
+
+    fn two_by_one() -> ImageTexture {
+        let pixels = vec![Color::new(0.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0)];
+        ImageTexture::new(2, 1, pixels).unwrap()
+    }
+
+    #[test]
+    fn nearest_filtering_picks_containing_texel() {
+        let texture = two_by_one().with_filter(FilterMode::Nearest);
+        assert_eq!(texture.value(0.2, 0.5, Point3::zeros()).x, 0.0);
+        assert_eq!(texture.value(0.8, 0.5, Point3::zeros()).x, 1.0);
+        // Repeating wraps past the right edge back to the first texel.
+        assert_eq!(texture.value(1.2, 0.5, Point3::zeros()).x, 0.0);
+        assert_eq!(texture.value(-0.2, 0.5, Point3::zeros()).x, 1.0);
+    }
+
+    #[test]
+    fn extreme_coordinates_give_finite_colors() {
+        for &wrap in &[WrapMode::Repeat, WrapMode::Clamp] {
+            for &filter in &[FilterMode::Nearest, FilterMode::Bilinear] {
+                let texture = two_by_one().with_wrap(wrap).with_filter(filter);
+                for &(u, v) in &[
+                    (f64::NAN, 0.5),
+                    (0.5, f64::NAN),
+                    (f64::INFINITY, 0.5),
+                    (0.5, f64::NEG_INFINITY),
+                    (1e300, -1e300),
+                ] {
+                    let color = texture.value(u, v, Point3::zeros());
+                    assert!(color.is_finite(), "{:?} at ({}, {})", color, u, v);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bilinear_filtering_interpolates_between_centers() {
+        let texture = two_by_one();
+        assert_float_eq!(
+            texture.value(0.25, 0.5, Point3::zeros()).x,
+            0.0,
+            abs <= 1e-12
+        );
+        assert_float_eq!(
+            texture.value(0.5, 0.5, Point3::zeros()).x,
+            0.5,
+            abs <= 1e-12
+        );
+        assert_float_eq!(
+            texture.value(0.75, 0.5, Point3::zeros()).x,
+            1.0,
+            abs <= 1e-12
+        );
+
+        let clamped = two_by_one().with_wrap(WrapMode::Clamp);
+        assert_float_eq!(
+            clamped.value(1.0, 0.5, Point3::zeros()).x,
+            1.0,
+            abs <= 1e-12
+        );
+        assert_float_eq!(
+            clamped.value(-3.0, 0.5, Point3::zeros()).x,
+            0.0,
+            abs <= 1e-12
+        );
+    }
+
+    #[test]
+    fn noise_modes_stay_in_unit_range() {
+        for &mode in &[NoiseMode::Plain, NoiseMode::Turbulence, NoiseMode::Marble] {
+            let texture = NoiseTexture::with_scale(4.0).with_mode(mode);
+            for i in 0..200 {
+                let point = Point3::new(-0.37 * i as f64, 0.11 * i as f64, -0.53 * i as f64);
+                let value = texture.value(0.0, 0.0, point).x;
+                assert!((0.0..=1.0).contains(&value), "{:?} gave {}", mode, value);
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_mismatched_pixel_count() {
+        assert!(ImageTexture::new(2, 2, vec![Color::zeros(); 3]).is_err());
     }
 }
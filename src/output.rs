@@ -0,0 +1,279 @@
+//! Writing rendered images to disk.
+
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{format_err, Context};
+
+use crate::film::{Aov, Film};
+use crate::vec3::Color;
+
+/// A file format that rendered images can be saved in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// 8-bit sRGB PNG.
+    Png,
+    /// 8-bit sRGB binary PPM (`P6`).
+    Ppm,
+    /// 32-bit floating point PFM, holding linear values without clamping.
+    Pfm,
+}
+
+impl ImageFormat {
+    /// Returns whether the format stores unbounded linear radiance rather than display colors.
+    pub fn is_hdr(self) -> bool {
+        matches!(self, ImageFormat::Pfm)
+    }
+
+    /// Picks the format from the extension of `path`, ignoring case.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(OsStr::to_str)
+            .map(str::to_ascii_lowercase);
+
+        match extension.as_deref() {
+            Some("png") => Ok(ImageFormat::Png),
+            Some("ppm") => Ok(ImageFormat::Ppm),
+            Some("pfm") => Ok(ImageFormat::Pfm),
+            _ => Err(format_err!(
+                "Cannot infer image format of {}, expected .png, .ppm or .pfm",
+                path.display()
+            )),
+        }
+    }
+}
+
+/// Saves the linear `pixels` returned by `render::render()` to `path`.
+///
+/// The format is chosen by the file extension. Pixels are in row-major order, starting from the
+/// top-left corner.
+pub fn save<P: AsRef<Path>>(
+    path: P,
+    pixels: &[Color],
+    width: usize,
+    height: usize,
+) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    let format = ImageFormat::from_path(path)?;
+    let file =
+        File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+
+    let mut writer = BufWriter::new(file);
+    write(&mut writer, format, pixels, width, height)
+        .and_then(|_| writer.flush().map_err(Into::into))
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Saves each of the `aovs` of `film` next to the image at `path`, in the same format.
+///
+/// HDR formats receive the raw values from `Film::channel()`, and the others the display mapping
+/// from `Film::preview()`.
+pub fn save_aovs<P: AsRef<Path>>(path: P, film: &Film, aovs: &[Aov]) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    let format = ImageFormat::from_path(path)?;
+    for &aov in aovs {
+        let pixels = if format.is_hdr() {
+            film.channel(aov)
+        } else {
+            film.preview(aov)
+        };
+        save(aov_path(path, aov), &pixels, film.width, film.height)?;
+    }
+    Ok(())
+}
+
+/// Returns where an AOV of the image at `path` is saved, e.g. `out/image.albedo.png` for
+/// `out/image.png`.
+pub fn aov_path<P: AsRef<Path>>(path: P, aov: Aov) -> PathBuf {
+    let path = path.as_ref();
+    let mut file_name = path.file_stem().unwrap_or_default().to_os_string();
+    file_name.push(".");
+    file_name.push(aov.name());
+    if let Some(extension) = path.extension() {
+        file_name.push(".");
+        file_name.push(extension);
+    }
+    path.with_file_name(file_name)
+}
+
+/// Encodes `pixels` in the given format.
+pub fn write<W: Write>(
+    writer: W,
+    format: ImageFormat,
+    pixels: &[Color],
+    width: usize,
+    height: usize,
+) -> anyhow::Result<()> {
+    if width == 0 || height == 0 {
+        return Err(format_err!(
+            "Cannot write an empty {}x{} image",
+            width,
+            height
+        ));
+    }
+    if pixels.len() != width * height {
+        return Err(format_err!(
+            "Expected {}x{} pixels, found {}",
+            width,
+            height,
+            pixels.len()
+        ));
+    }
+
+    match format {
+        ImageFormat::Png => write_png(writer, pixels, width, height),
+        ImageFormat::Ppm => write_ppm(writer, pixels, width, height),
+        ImageFormat::Pfm => write_pfm(writer, pixels, width, height),
+    }
+}
+
+fn write_png<W: Write>(writer: W, pixels: &[Color], w: usize, h: usize) -> anyhow::Result<()> {
+    let mut encoder = png::Encoder::new(writer, w as u32, h as u32);
+    encoder.set_color(png::ColorType::RGB);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&to_srgb_bytes(pixels))?;
+    Ok(())
+}
+
+fn write_ppm<W: Write>(mut writer: W, pixels: &[Color], w: usize, h: usize) -> anyhow::Result<()> {
+    write!(writer, "P6\n{} {}\n255\n", w, h)?;
+    writer.write_all(&to_srgb_bytes(pixels))?;
+    Ok(())
+}
+
+fn write_pfm<W: Write>(mut writer: W, pixels: &[Color], w: usize, h: usize) -> anyhow::Result<()> {
+    // A negative scale marks the data as little-endian. Rows are stored from the bottom up.
+    write!(writer, "PF\n{} {}\n-1.0\n", w, h)?;
+    for row in pixels.chunks(w).rev() {
+        for pixel in row {
+            for &channel in &[pixel.x, pixel.y, pixel.z] {
+                writer.write_all(&(channel as f32).to_le_bytes())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Converts a single linear channel value in `0.0..=1.0` to its sRGB encoding.
+#[inline]
+pub fn linear_to_srgb(value: f64) -> f64 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn to_srgb_bytes(pixels: &[Color]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(pixels.len() * 3);
+    for pixel in pixels {
+        for &channel in &[pixel.x, pixel.y, pixel.z] {
+            bytes.push((linear_to_srgb(channel.clamp(0.0, 1.0)) * 255.0).round() as u8);
+        }
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use super::*;
+
+    fn pixels() -> Vec<Color> {
+        vec![
+            Color::new(0.0, 0.5, 1.0),
+            Color::new(2.0, -1.0, 0.25),
+            Color::new(0.0, 0.0, 0.0),
+            Color::new(1.0, 1.0, 1.0),
+        ]
+    }
+
+    #[test]
+    fn infers_format_from_extension() {
+        assert_eq!(ImageFormat::from_path("a/b.PNG").unwrap(), ImageFormat::Png);
+        assert_eq!(ImageFormat::from_path("b.ppm").unwrap(), ImageFormat::Ppm);
+        assert_eq!(ImageFormat::from_path("b.pfm").unwrap(), ImageFormat::Pfm);
+        assert!(ImageFormat::from_path("b.jpg").is_err());
+        assert!(ImageFormat::from_path("b").is_err());
+    }
+
+    #[test]
+    fn writes_binary_ppm() {
+        let mut bytes = Vec::new();
+        write(&mut bytes, ImageFormat::Ppm, &pixels(), 2, 2).unwrap();
+
+        let header = b"P6\n2 2\n255\n";
+        assert_eq!(&bytes[..header.len()], header);
+        assert_eq!(
+            &bytes[header.len()..],
+            &[0, 188, 255, 255, 0, 137, 0, 0, 0, 255, 255, 255]
+        );
+    }
+
+    #[test]
+    fn writes_bottom_up_pfm() {
+        let mut bytes = Vec::new();
+        write(&mut bytes, ImageFormat::Pfm, &pixels(), 2, 2).unwrap();
+
+        let header = b"PF\n2 2\n-1.0\n";
+        assert_eq!(&bytes[..header.len()], header);
+        let floats: Vec<f32> = bytes[header.len()..]
+            .chunks(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        assert_eq!(floats.len(), 12);
+        // The bottom row comes first, and values are neither clamped nor encoded.
+        assert_eq!(&floats[..3], &[0.0, 0.0, 0.0]);
+        assert_eq!(&floats[6..9], &[0.0, 0.5, 1.0]);
+        assert_eq!(&floats[9..], &[2.0, -1.0, 0.25]);
+    }
+
+    #[test]
+    fn writes_decodable_png() {
+        let mut bytes = Vec::new();
+        write(&mut bytes, ImageFormat::Png, &pixels(), 2, 2).unwrap();
+
+        let (info, mut reader) = png::Decoder::new(&bytes[..]).read_info().unwrap();
+        let mut buffer = vec![0; info.buffer_size()];
+        reader.next_frame(&mut buffer).unwrap();
+        assert_eq!((info.width, info.height), (2, 2));
+        assert_eq!(&buffer[..3], &[0, 188, 255]);
+    }
+
+    #[test]
+    fn names_aov_files_after_the_image() {
+        assert_eq!(
+            aov_path("out/image.png", Aov::Albedo),
+            Path::new("out/image.albedo.png")
+        );
+        assert_eq!(
+            aov_path("image.v2.pfm", Aov::ObjectId),
+            Path::new("image.v2.object-id.pfm")
+        );
+    }
+
+    #[test]
+    fn srgb_transfer() {
+        assert_eq!(linear_to_srgb(0.0), 0.0);
+        assert_float_eq!(linear_to_srgb(1.0), 1.0, abs <= 1e-12);
+        assert_float_eq!(linear_to_srgb(0.214_041), 0.5, abs <= 1e-6);
+        // The linear segment near black is steeper than a pure power curve.
+        assert_float_eq!(linear_to_srgb(0.001), 0.012_92, abs <= 1e-12);
+    }
+
+    #[test]
+    fn rejects_mismatched_dimensions() {
+        assert!(write(Vec::new(), ImageFormat::Png, &pixels(), 3, 2).is_err());
+        for &format in &[ImageFormat::Png, ImageFormat::Ppm, ImageFormat::Pfm] {
+            assert!(write(Vec::new(), format, &[], 0, 0).is_err());
+            assert!(write(Vec::new(), format, &[], 0, 3).is_err());
+        }
+    }
+}
@@ -1,17 +1,43 @@
+use std::collections::HashMap;
+
 use indicatif::{ParallelProgressIterator, ProgressBar, ProgressStyle};
-use rand::{rngs::ThreadRng, Rng};
+use rand::Rng;
 use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
 
 use crate::camera::Camera;
-use crate::geom::Hittable;
-use crate::mat::Scatter;
+use crate::film::{Aov, Film};
+use crate::geom;
+use crate::integrator::{Integrator, MIN_HIT_DISTANCE};
+use crate::random;
 use crate::ray::Ray;
 use crate::scene::{Scene, Sky};
-use crate::vec3::Color;
+use crate::vec3::{Color, Point3, Vec3};
 
 const MAX_SEQUENTIAL: u32 = 350;
 
-pub fn render<S: Sky>(scene: &Scene<S>, camera: &Camera, w: usize, h: usize) -> Vec<Color> {
+/// Renders the scene into a `Film`, holding the image along with the requested `aovs`.
+///
+/// Each pixel holds the mean of its `scene.samples_per_pixel` samples, each of which is the
+/// `integrator`'s estimate for a ray through a random point in the pixel. The AOVs describe the
+/// first surface each of these rays hits, which costs another intersection test per sample, so
+/// it is only looked up if any of `aovs` needs it. AOVs that aren't requested are left at zero.
+///
+/// Each pixel draws from its own generator seeded from `scene.seed` and the pixel's position,
+/// and samples are always summed in the same order, so the output is identical for a given seed
+/// no matter how many threads rayon uses.
+pub fn render<S, C, I>(
+    scene: &Scene<S>,
+    camera: &C,
+    integrator: &I,
+    w: usize,
+    h: usize,
+    aovs: &[Aov],
+) -> Film
+where
+    S: Sky,
+    C: Camera + ?Sized,
+    I: Integrator<S> + ?Sized,
+{
     console::set_colors_enabled(true);
 
     let bar = ProgressBar::new(h as u64).with_style(
@@ -19,49 +45,315 @@ pub fn render<S: Sky>(scene: &Scene<S>, camera: &Camera, w: usize, h: usize) ->
             .template("Rendering: [{eta_precise}] {bar:40.cyan/blue} {pos:>7}/{len:} scanlines"),
     );
 
-    (0..h)
+    let trace_first_hit = aovs.iter().any(|&aov| aov != Aov::SampleCount);
+
+    let pixels: Vec<Pixel> = (0..h)
         .into_par_iter()
         .rev()
         .progress_with(bar)
         .flat_map(|j| {
             (0..w).into_par_iter().map(move |i| {
-                let collect_sample = move |rng: &mut ThreadRng| {
+                let pixel_seed = random::mix(scene.seed, (j * w + i) as u64);
+                let collect_sample = move |rng: &mut random::SampleRng| {
                     let u = (i as f64 + rng.gen::<f64>()) / (w - 1) as f64;
                     let v = (j as f64 + rng.gen::<f64>()) / (h - 1) as f64;
-                    let ray = camera.ray_at(u, v);
-                    compute_ray_color(scene, &ray, scene.max_bounce_depth)
+                    let ray = camera.ray_at(u, v, rng);
+                    Sample {
+                        radiance: integrator.radiance(scene, &ray, rng),
+                        first_hit: if trace_first_hit {
+                            FirstHit::trace(scene, &ray)
+                        } else {
+                            None
+                        },
+                    }
                 };
 
                 if scene.samples_per_pixel < MAX_SEQUENTIAL {
-                    let mut rng = rand::thread_rng();
-                    (0..scene.samples_per_pixel)
-                        .map(move |_| collect_sample(&mut rng))
-                        .sum()
+                    let mut rng = random::stream_rng(pixel_seed, 0);
+                    (0..scene.samples_per_pixel).fold(Pixel::default(), |pixel, _| {
+                        pixel.add(collect_sample(&mut rng))
+                    })
                 } else {
-                    (0..scene.samples_per_pixel)
+                    // Give every sample its own stream, and sum them in order afterwards, since a
+                    // parallel reduction would group the additions differently on each run.
+                    let samples: Vec<Sample> = (0..scene.samples_per_pixel)
                         .into_par_iter()
-                        .map_init(rand::thread_rng, move |rng, _| collect_sample(rng))
-                        .sum()
+                        .map(move |sample| {
+                            collect_sample(&mut random::stream_rng(pixel_seed, sample as u64 + 1))
+                        })
+                        .collect();
+                    samples.into_iter().fold(Pixel::default(), Pixel::add)
                 }
             })
         })
-        .collect()
+        .collect();
+
+    let mut film = Film::new(w, h);
+    // Material addresses change between runs, so they are numbered in order of appearance.
+    let mut material_ids = HashMap::new();
+    for (index, pixel) in pixels.into_iter().enumerate() {
+        let count = pixel.count as f64;
+        film.beauty[index] = pixel.radiance / count;
+        film.albedo[index] = pixel.albedo / count;
+        film.normal[index] = pixel.normal / count;
+        if pixel.hits > 0 {
+            film.depth[index] = pixel.depth / pixel.hits as f64;
+            film.position[index] = pixel.position / pixel.hits as f64;
+        }
+        if let Some((object, material)) = pixel.first_ids {
+            let next_id = material_ids.len() as u32 + 1;
+            film.object_id[index] = object as u32 + 1;
+            film.material_id[index] = *material_ids.entry(material).or_insert(next_id);
+        }
+        film.sample_count[index] = pixel.count;
+    }
+    film
+}
+
+/// What a single camera ray sees.
+struct Sample {
+    radiance: Color,
+    first_hit: Option<FirstHit>,
+}
+
+/// The properties of the first surface a camera ray hits, as recorded in the AOVs.
+struct FirstHit {
+    albedo: Color,
+    normal: Vec3,
+    depth: f64,
+    position: Point3,
+    object_index: usize,
+    material_instance: usize,
 }
 
-fn compute_ray_color<S: Sky>(scene: &Scene<S>, ray: &Ray, depth: u32) -> Color {
-    if depth <= 0 {
-        // If we've exceeded the ray bounce limit, no more light is gathered.
-        return Color::zeros();
+impl FirstHit {
+    fn trace<S: Sky>(scene: &Scene<S>, ray: &Ray) -> Option<Self> {
+        let (object_index, hit) =
+            geom::closest_hit(&scene.world, ray, (MIN_HIT_DISTANCE, f64::MAX))?;
+        Some(FirstHit {
+            albedo: hit.material.albedo(&hit),
+            normal: hit.normal.to_unit(),
+            depth: hit.t * ray.direction.len(),
+            position: hit.point,
+            object_index,
+            material_instance: hit.material.instance_id(),
+        })
     }
+}
 
-    if let Some(hit_record) = scene.world.hit(ray, (0.001, std::f64::MAX)) {
-        if let Some(scatter) = hit_record.material.scatter(ray, &hit_record) {
-            let Scatter { ray, attenuation } = scatter;
-            return attenuation * compute_ray_color(scene, &ray, depth - 1);
-        } else {
-            return Color::zeros();
+/// The sums of a pixel's samples.
+#[derive(Default)]
+struct Pixel {
+    count: u32,
+    radiance: Color,
+    albedo: Color,
+    normal: Vec3,
+    /// The number of samples hitting anything, which `depth` and `position` are summed over.
+    hits: u32,
+    depth: f64,
+    position: Point3,
+    /// The object index and material instance hit by the first sample that hits anything.
+    first_ids: Option<(usize, usize)>,
+}
+
+impl Pixel {
+    fn add(mut self, sample: Sample) -> Self {
+        if let Some(hit) = sample.first_hit {
+            if self.first_ids.is_none() {
+                self.first_ids = Some((hit.object_index, hit.material_instance));
+            }
+            self.albedo += hit.albedo;
+            self.normal += hit.normal;
+            self.hits += 1;
+            self.depth += hit.depth;
+            self.position += hit.position;
         }
+        self.radiance += sample.radiance;
+        self.count += 1;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::camera::{OrthographicCamera, PerspectiveCamera};
+    use crate::geom::{Hittable, MovingSphere, Sphere, XyRect};
+    use crate::integrator::PathTracer;
+    use crate::mat::{Lambertian, Metallic, Texture};
+    use crate::scene::GradientSky;
+
+    #[test]
+    fn output_depends_only_on_seed() {
+        let scene = |seed| {
+            let world: Vec<Box<dyn Hittable>> = vec![
+                Box::new(Sphere::new(
+                    Point3::new(0.0, 0.0, -1.0),
+                    0.5,
+                    Lambertian::default(),
+                )),
+                Box::new(Sphere::new(
+                    Point3::new(0.0, -100.5, -1.0),
+                    100.0,
+                    Lambertian::default(),
+                )),
+            ];
+            Scene::new(world, GradientSky::default())
+                .with_samples_per_pixel(4)
+                .with_seed(seed)
+        };
+        let render_with_threads = |seed, threads| {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap()
+                .install(|| {
+                    render(
+                        &scene(seed),
+                        &PerspectiveCamera::default(),
+                        &PathTracer::default(),
+                        16,
+                        9,
+                        &Aov::ALL,
+                    )
+                })
+        };
+
+        let single = render_with_threads(7, 1);
+        assert_eq!(single, render_with_threads(7, 4));
+        assert_ne!(single, render_with_threads(8, 4));
     }
 
-    scene.sky.color(ray)
+    /// Shows the texture coordinates as colors, so that invalid ones end up in the image.
+    #[derive(Debug)]
+    struct UvTexture;
+
+    impl Texture for UvTexture {
+        fn value(&self, u: f64, v: f64, _: Point3) -> Color {
+            Color::new(u, v, 0.0)
+        }
+    }
+
+    #[test]
+    fn textures_spheres_of_any_radius() {
+        let world: Vec<Box<dyn Hittable>> = vec![
+            Box::new(Sphere::new(
+                Point3::new(-2.5, 0.0, 0.0),
+                2.0,
+                Lambertian::new(UvTexture),
+            )),
+            Box::new(MovingSphere::new(
+                (Point3::new(2.5, 0.0, 0.0), Point3::new(2.5, 0.5, 0.0)),
+                (0.0, 1.0),
+                2.0,
+                Lambertian::new(UvTexture),
+            )),
+        ];
+        let scene = Scene::new(world, GradientSky::default()).with_samples_per_pixel(4);
+        let camera = OrthographicCamera::new(
+            Vec3::new(0.0, 1.0, 0.0),
+            Point3::new(0.0, 0.0, 10.0),
+            Point3::zeros(),
+            5.0,
+            2.0,
+            Duration::from_secs(1),
+        );
+        let film = render(&scene, &camera, &PathTracer::default(), 16, 8, &[]);
+
+        assert!(film.beauty.iter().all(|pixel| pixel.is_finite()));
+    }
+
+    #[test]
+    fn records_first_hit_aovs() {
+        // A sphere in front of a backdrop covering the lower half of the view.
+        let world: Vec<Box<dyn Hittable>> = vec![
+            Box::new(XyRect::new(
+                (-10.0, 10.0),
+                (-10.0, 0.0),
+                -2.0,
+                Lambertian::new(Color::new(0.2, 0.4, 0.6)),
+            )),
+            Box::new(Sphere::new(Point3::zeros(), 1.0, Metallic::default())),
+        ];
+        let scene = Scene::new(world, GradientSky::default()).with_samples_per_pixel(4);
+        let camera = OrthographicCamera::new(
+            Vec3::new(0.0, 1.0, 0.0),
+            Point3::new(0.0, 0.0, 5.0),
+            Point3::zeros(),
+            4.0,
+            1.0,
+            Duration::from_secs(0),
+        );
+        let film = render(&scene, &camera, &PathTracer::default(), 8, 8, &Aov::ALL);
+
+        let (top_left, center, bottom_left) = (0, 4 * 8 + 4, 7 * 8);
+        assert_eq!(film.sample_count, vec![4; 64]);
+
+        assert_eq!(film.object_id[top_left], 0);
+        assert_eq!(film.depth[top_left], 0.0);
+        assert_eq!(film.albedo[top_left], Color::zeros());
+
+        assert_eq!(film.object_id[center], 2);
+        assert_eq!(film.albedo[center], Metallic::default().albedo);
+        assert!(film.normal[center].z > 0.5);
+        assert!((4.0..5.0).contains(&film.depth[center]));
+
+        assert_eq!(film.object_id[bottom_left], 1);
+        assert_eq!(film.albedo[bottom_left], Color::new(0.2, 0.4, 0.6));
+        assert_eq!(film.normal[bottom_left], Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(film.position[bottom_left].z, -2.0);
+        assert_eq!(film.depth[bottom_left], 7.0);
+
+        // The sphere shows up first when scanning from the top.
+        assert_eq!(film.material_id[center], 1);
+        assert_eq!(film.material_id[bottom_left], 2);
+    }
+
+    #[test]
+    fn skips_first_hits_without_surface_aovs() {
+        let world: Vec<Box<dyn Hittable>> = vec![Box::new(Sphere::new(
+            Point3::new(0.0, 0.0, -1.0),
+            0.5,
+            Lambertian::default(),
+        ))];
+        let scene = Scene::new(world, GradientSky::default()).with_samples_per_pixel(2);
+        let film = render(
+            &scene,
+            &PerspectiveCamera::default(),
+            &PathTracer::default(),
+            8,
+            8,
+            &[Aov::SampleCount],
+        );
+
+        assert_eq!(film.sample_count, vec![2; 64]);
+        assert!(film.beauty.iter().all(|&pixel| pixel != Color::zeros()));
+        assert!(film.object_id.iter().all(|&id| id == 0));
+        assert!(film.depth.iter().all(|&depth| depth == 0.0));
+    }
+
+    #[test]
+    fn ids_come_from_the_first_sample_that_hits() {
+        let miss = Sample {
+            radiance: Color::ones(),
+            first_hit: None,
+        };
+        let hit = |object_index| Sample {
+            radiance: Color::zeros(),
+            first_hit: Some(FirstHit {
+                albedo: Color::ones(),
+                normal: Vec3::new(0.0, 0.0, 1.0),
+                depth: 1.0,
+                position: Point3::zeros(),
+                object_index,
+                material_instance: 0,
+            }),
+        };
+
+        let pixel = Pixel::default().add(miss).add(hit(3)).add(hit(5));
+        assert_eq!(pixel.first_ids, Some((3, 0)));
+        assert_eq!((pixel.count, pixel.hits), (3, 2));
+    }
 }
@@ -1,14 +1,29 @@
+pub use self::builtin::EARTH_TEXTURE_PATH;
+pub use self::file::SceneDescription;
+
+use std::fmt::Debug;
+
 use crate::geom::Hittable;
 use crate::ray::Ray;
 use crate::vec3::Color;
 
+mod builtin;
+mod file;
+
 const MAX_BOUNCE_DEPTH: u32 = 50;
+const MIN_BOUNCE_DEPTH: u32 = 3;
 const SAMPLES_PER_PIXEL: u32 = 100;
 
-pub trait Sky: Send + Sync {
+pub trait Sky: Debug + Send + Sync {
     fn color(&self, incoming: &Ray) -> Color;
 }
 
+impl<S: Sky + ?Sized> Sky for Box<S> {
+    fn color(&self, incoming: &Ray) -> Color {
+        (**self).color(incoming)
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct GradientSky {
     pub color: Color,
@@ -52,32 +67,57 @@ impl Sky for SolidSky {
 #[derive(Debug)]
 pub struct Scene<S: Sky> {
     pub world: Vec<Box<dyn Hittable>>,
+    /// Emitters that integrators may sample directly, which must also be part of `world`.
+    ///
+    /// These only serve to pick directions towards the lights, while their emission is always
+    /// found by tracing rays through `world`. A `Shared` object can be placed in both lists.
+    pub lights: Vec<Box<dyn Hittable>>,
     pub sky: S,
     pub max_bounce_depth: u32,
+    /// Number of bounces a path always makes before Russian roulette may terminate it early.
+    pub min_bounce_depth: u32,
     pub samples_per_pixel: u32,
+    /// Seeds every random decision made while rendering, so equal seeds give identical images.
+    pub seed: u64,
 }
 
 impl<S: Sky> Scene<S> {
     pub fn new(world: Vec<Box<dyn Hittable>>, sky: S) -> Self {
         Scene {
             world,
+            lights: Vec::new(),
             sky,
             max_bounce_depth: MAX_BOUNCE_DEPTH,
+            min_bounce_depth: MIN_BOUNCE_DEPTH,
             samples_per_pixel: SAMPLES_PER_PIXEL,
+            seed: 0,
         }
     }
 
-    #[allow(unused)]
+    pub fn with_lights(mut self, val: Vec<Box<dyn Hittable>>) -> Self {
+        self.lights = val;
+        self
+    }
+
     pub fn with_max_bounces(mut self, val: u32) -> Self {
         self.max_bounce_depth = val;
         self
     }
 
-    #[allow(unused)]
+    pub fn with_min_bounces(mut self, val: u32) -> Self {
+        self.min_bounce_depth = val;
+        self
+    }
+
     pub fn with_samples_per_pixel(mut self, val: u32) -> Self {
         self.samples_per_pixel = val;
         self
     }
+
+    pub fn with_seed(mut self, val: u64) -> Self {
+        self.seed = val;
+        self
+    }
 }
 
 impl Default for Scene<GradientSky> {
@@ -0,0 +1,411 @@
+//! Scenes from the books, available by name.
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{format_err, Context};
+use rand::Rng;
+
+use super::{GradientSky, Scene, SceneDescription, Sky, SolidSky};
+use crate::camera::CameraSettings;
+use crate::geom::{
+    Affine, ConstantMedium, Cuboid, FlipFace, Hittable, LinearBvh, MovingSphere, Shared, Sphere,
+    Transform, XyRect, XzRect, YzRect,
+};
+use crate::mat::{
+    CheckeredTexture, Dielectric, DiffuseLight, ImageTexture, Lambertian, Metallic, NoiseMode,
+    NoiseTexture,
+};
+use crate::random;
+use crate::vec3::{Color, Point3, Vec3};
+
+/// Texture used by the scenes featuring the earth, relative to the working directory.
+///
+/// It is not distributed with the renderer; any equirectangular map of the earth in PNG or PPM
+/// format will do. Without it, the scenes fall back to a coarse procedural globe.
+pub const EARTH_TEXTURE_PATH: &str = "assets/earthmap.png";
+
+/// Seed for the random placement of objects, kept fixed so that each scene always looks the same.
+const LAYOUT_SEED: u64 = 0;
+
+type Builder = fn() -> anyhow::Result<SceneDescription>;
+
+const BUILTIN_SCENES: &[(&str, Builder)] = &[
+    ("random-spheres", random_spheres),
+    ("checkered-spheres", checkered_spheres),
+    ("two-perlin-spheres", two_perlin_spheres),
+    ("earth", earth),
+    ("simple-light", simple_light),
+    ("cornell-box", cornell_box),
+    ("final-scene", final_scene),
+];
+
+impl SceneDescription {
+    /// Builds one of the scenes from the books by name. See `builtin_names()` for the choices.
+    pub fn builtin(name: &str) -> anyhow::Result<Self> {
+        let (_, builder) = BUILTIN_SCENES
+            .iter()
+            .find(|(scene, _)| *scene == name)
+            .ok_or_else(|| {
+                let names: Vec<_> = SceneDescription::builtin_names().collect();
+                format_err!(
+                    "Unknown scene `{}`, expected one of: {}",
+                    name,
+                    names.join(", ")
+                )
+            })?;
+
+        builder().with_context(|| format!("Failed to build scene `{}`", name))
+    }
+
+    pub fn builtin_names() -> impl Iterator<Item = &'static str> {
+        BUILTIN_SCENES.iter().map(|&(name, _)| name)
+    }
+}
+
+fn describe<S: Sky + 'static>(
+    world: Vec<Box<dyn Hittable>>,
+    sky: S,
+    camera: CameraSettings,
+) -> SceneDescription {
+    SceneDescription::new(Scene::new(world, Box::new(sky)), camera).with_resolution(400, 225)
+}
+
+fn earth_texture() -> anyhow::Result<ImageTexture> {
+    if Path::new(EARTH_TEXTURE_PATH).exists() {
+        return ImageTexture::open(EARTH_TEXTURE_PATH);
+    }
+
+    // Polar caps above and below bands of scattered land and ocean.
+    let (ice, land, ocean) = (
+        Color::new(0.9, 0.9, 0.9),
+        Color::new(0.2, 0.45, 0.15),
+        Color::new(0.05, 0.15, 0.5),
+    );
+    let (width, height) = (8, 4);
+    let pixels = (0..height)
+        .flat_map(|row| {
+            (0..width).map(move |column| match row {
+                0 | 3 => ice,
+                _ if (row + column) % 3 == 0 => land,
+                _ => ocean,
+            })
+        })
+        .collect();
+    ImageTexture::new(width, height, pixels)
+}
+
+/// The cover of _Ray Tracing in One Weekend_.
+fn random_spheres() -> anyhow::Result<SceneDescription> {
+    let mut rng = random::stream_rng(LAYOUT_SEED, 0);
+    let mut world: Vec<Box<dyn Hittable>> = Vec::new();
+
+    for a in -11..11 {
+        for b in -11..11 {
+            let choose_mat: f64 = rng.gen();
+            let center = Point3::new(
+                a as f64 + 0.9 * rng.gen::<f64>(),
+                0.2,
+                b as f64 + 0.9 * rng.gen::<f64>(),
+            );
+
+            if (center - Point3::new(4.0, 0.2, 0.0)).len() <= 0.9 {
+                continue;
+            }
+
+            if choose_mat < 0.8 {
+                let albedo = Color::random(&mut rng) * Color::random(&mut rng);
+                world.push(Box::new(Sphere::new(center, 0.2, Lambertian::new(albedo))));
+            } else if choose_mat < 0.95 {
+                let albedo = Color::random_in_range(&mut rng, 0.5, 1.0);
+                let fuzz = rng.gen_range(0.0, 0.5);
+                world.push(Box::new(Sphere::new(
+                    center,
+                    0.2,
+                    Metallic::new(albedo, fuzz),
+                )));
+            } else {
+                world.push(Box::new(Sphere::new(center, 0.2, Dielectric::new(1.5))));
+            }
+        }
+    }
+
+    world.push(Box::new(Sphere::new(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        Dielectric::new(1.5),
+    )));
+    world.push(Box::new(Sphere::new(
+        Point3::new(-4.0, 1.0, 0.0),
+        1.0,
+        Lambertian::new(Color::new(0.4, 0.2, 0.1)),
+    )));
+    world.push(Box::new(Sphere::new(
+        Point3::new(4.0, 1.0, 0.0),
+        1.0,
+        Metallic::new(Color::new(0.7, 0.6, 0.5), 0.0),
+    )));
+
+    let ground: Box<dyn Hittable> = Box::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Lambertian::new(Color::new(0.5, 0.5, 0.5)),
+    ));
+    let world = vec![ground, Box::new(LinearBvh::new(world, 0.0, 0.0)?)];
+
+    let camera = CameraSettings::new(Point3::new(13.0, 2.0, 3.0), Point3::zeros(), 20.0)
+        .with_aperture(0.1)
+        .with_focus_dist(10.0);
+
+    Ok(describe(world, GradientSky::default(), camera))
+}
+
+fn checkered_spheres() -> anyhow::Result<SceneDescription> {
+    let checker = CheckeredTexture::new(Color::new(0.2, 0.3, 0.1), Color::new(0.9, 0.9, 0.9));
+    let world: Vec<Box<dyn Hittable>> = vec![
+        Box::new(Sphere::new(
+            Point3::new(0.0, -10.0, 0.0),
+            10.0,
+            Lambertian::new(checker),
+        )),
+        Box::new(Sphere::new(
+            Point3::new(0.0, 10.0, 0.0),
+            10.0,
+            Lambertian::new(checker),
+        )),
+    ];
+
+    let camera = CameraSettings::new(Point3::new(13.0, 2.0, 3.0), Point3::zeros(), 20.0);
+    Ok(describe(world, GradientSky::default(), camera))
+}
+
+fn perlin_spheres(noise: NoiseTexture) -> Vec<Box<dyn Hittable>> {
+    vec![
+        Box::new(Sphere::new(
+            Point3::new(0.0, -1000.0, 0.0),
+            1000.0,
+            Lambertian::new(noise.clone()),
+        )),
+        Box::new(Sphere::new(
+            Point3::new(0.0, 2.0, 0.0),
+            2.0,
+            Lambertian::new(noise),
+        )),
+    ]
+}
+
+fn two_perlin_spheres() -> anyhow::Result<SceneDescription> {
+    let camera = CameraSettings::new(Point3::new(13.0, 2.0, 3.0), Point3::zeros(), 20.0)
+        .with_focus_dist(10.0)
+        .with_shutter_duration(Duration::from_secs(1));
+    let world = perlin_spheres(NoiseTexture::with_scale(3.0));
+    Ok(describe(world, GradientSky::default(), camera).with_resolution(384, 216))
+}
+
+fn earth() -> anyhow::Result<SceneDescription> {
+    let globe = Sphere::new(Point3::zeros(), 2.0, Lambertian::new(earth_texture()?));
+    let camera = CameraSettings::new(Point3::new(0.0, 0.0, 12.0), Point3::zeros(), 20.0);
+    Ok(describe(
+        vec![Box::new(globe)],
+        GradientSky::default(),
+        camera,
+    ))
+}
+
+fn simple_light() -> anyhow::Result<SceneDescription> {
+    let light: Arc<dyn Hittable> = Arc::new(XyRect::new(
+        (3.0, 5.0),
+        (1.0, 3.0),
+        -2.0,
+        DiffuseLight::new(Color::new(4.0, 4.0, 4.0)),
+    ));
+    let mut world = perlin_spheres(NoiseTexture::with_scale(4.0).with_mode(NoiseMode::Marble));
+    world.push(Box::new(Shared::new(light.clone())));
+
+    let camera = CameraSettings::new(
+        Point3::new(26.0, 3.0, 6.0),
+        Point3::new(0.0, 2.0, 0.0),
+        20.0,
+    );
+    let mut description = describe(world, SolidSky::default(), camera);
+    description.scene.lights = vec![Box::new(Shared::new(light))];
+    description.scene.samples_per_pixel = 400;
+    Ok(description)
+}
+
+fn cornell_box() -> anyhow::Result<SceneDescription> {
+    let red = Lambertian::new(Color::new(0.65, 0.05, 0.05));
+    let white = Lambertian::new(Color::new(0.73, 0.73, 0.73));
+    let green = Lambertian::new(Color::new(0.12, 0.45, 0.15));
+    let light: Arc<dyn Hittable> = Arc::new(FlipFace::new(XzRect::new(
+        (213.0, 343.0),
+        (227.0, 332.0),
+        554.0,
+        DiffuseLight::new(Color::new(15.0, 15.0, 15.0)),
+    )));
+
+    let tall_box: Box<dyn Hittable> = Box::new(Cuboid::new(
+        Point3::zeros(),
+        Point3::new(165.0, 330.0, 165.0),
+        white.clone(),
+    ));
+    let short_box: Box<dyn Hittable> = Box::new(Cuboid::new(
+        Point3::zeros(),
+        Point3::new(165.0, 165.0, 165.0),
+        white.clone(),
+    ));
+
+    let world: Vec<Box<dyn Hittable>> = vec![
+        Box::new(YzRect::new((0.0, 555.0), (0.0, 555.0), 555.0, green)),
+        Box::new(YzRect::new((0.0, 555.0), (0.0, 555.0), 0.0, red)),
+        Box::new(Shared::new(light.clone())),
+        Box::new(XzRect::new((0.0, 555.0), (0.0, 555.0), 0.0, white.clone())),
+        Box::new(XzRect::new(
+            (0.0, 555.0),
+            (0.0, 555.0),
+            555.0,
+            white.clone(),
+        )),
+        Box::new(XyRect::new((0.0, 555.0), (0.0, 555.0), 555.0, white)),
+        Box::new(placed(tall_box, 15.0, Vec3::new(265.0, 0.0, 295.0))?),
+        Box::new(placed(short_box, -18.0, Vec3::new(130.0, 0.0, 65.0))?),
+    ];
+
+    let camera = CameraSettings::new(
+        Point3::new(278.0, 278.0, -800.0),
+        Point3::new(278.0, 278.0, 0.0),
+        40.0,
+    );
+    let mut description = describe(world, SolidSky::default(), camera).with_resolution(600, 600);
+    description.scene.lights = vec![Box::new(Shared::new(light))];
+    description.scene.samples_per_pixel = 200;
+    Ok(description)
+}
+
+/// Rotates `object` about the y axis, then moves it by `offset`.
+fn placed(object: Box<dyn Hittable>, angle_deg: f64, offset: Vec3) -> anyhow::Result<Transform> {
+    let rotation = Affine::rotation(Vec3::new(0.0, 1.0, 0.0), angle_deg);
+    Transform::new(object, rotation.then(Affine::translation(offset)))
+}
+
+/// The cover of _Ray Tracing: The Next Week_.
+fn final_scene() -> anyhow::Result<SceneDescription> {
+    let mut rng = random::stream_rng(LAYOUT_SEED, 1);
+    let mut world: Vec<Box<dyn Hittable>> = Vec::new();
+
+    let ground = Lambertian::new(Color::new(0.48, 0.83, 0.53));
+    let boxes_per_side = 20;
+    let mut boxes: Vec<Box<dyn Hittable>> = Vec::new();
+    for i in 0..boxes_per_side {
+        for j in 0..boxes_per_side {
+            let w = 100.0;
+            let x0 = -1000.0 + i as f64 * w;
+            let z0 = -1000.0 + j as f64 * w;
+            let y1 = rng.gen_range(1.0, 101.0);
+            boxes.push(Box::new(Cuboid::new(
+                Point3::new(x0, 0.0, z0),
+                Point3::new(x0 + w, y1, z0 + w),
+                ground.clone(),
+            )));
+        }
+    }
+    world.push(Box::new(LinearBvh::new(boxes, 0.0, 1.0)?));
+
+    let light: Arc<dyn Hittable> = Arc::new(XzRect::new(
+        (123.0, 423.0),
+        (147.0, 412.0),
+        554.0,
+        DiffuseLight::new(Color::new(7.0, 7.0, 7.0)),
+    ));
+    world.push(Box::new(Shared::new(light.clone())));
+
+    let center = Point3::new(400.0, 400.0, 200.0);
+    world.push(Box::new(MovingSphere::new(
+        (center, center + Vec3::new(30.0, 0.0, 0.0)),
+        (0.0, 1.0),
+        50.0,
+        Lambertian::new(Color::new(0.7, 0.3, 0.1)),
+    )));
+    world.push(Box::new(Sphere::new(
+        Point3::new(260.0, 150.0, 45.0),
+        50.0,
+        Dielectric::new(1.5),
+    )));
+    world.push(Box::new(Sphere::new(
+        Point3::new(0.0, 150.0, 145.0),
+        50.0,
+        Metallic::new(Color::new(0.8, 0.8, 0.9), 1.0),
+    )));
+
+    // A glass ball filled with blue smoke, and a thin mist over the whole scene.
+    let subsurface = || Sphere::new(Point3::new(360.0, 150.0, 145.0), 70.0, Dielectric::new(1.5));
+    world.push(Box::new(subsurface()));
+    world.push(Box::new(ConstantMedium::new(
+        Box::new(subsurface()),
+        0.2,
+        Color::new(0.2, 0.4, 0.9),
+    )));
+    let mist = Sphere::new(Point3::zeros(), 5000.0, Dielectric::new(1.5));
+    world.push(Box::new(ConstantMedium::new(
+        Box::new(mist),
+        0.0001,
+        Color::ones(),
+    )));
+
+    world.push(Box::new(Sphere::new(
+        Point3::new(400.0, 200.0, 400.0),
+        100.0,
+        Lambertian::new(earth_texture()?),
+    )));
+    world.push(Box::new(Sphere::new(
+        Point3::new(220.0, 280.0, 300.0),
+        80.0,
+        Lambertian::new(NoiseTexture::with_scale(0.1).with_mode(NoiseMode::Marble)),
+    )));
+
+    let white = Lambertian::new(Color::new(0.73, 0.73, 0.73));
+    let cluster: Vec<Box<dyn Hittable>> = (0..1000)
+        .map(|_| {
+            let center = Point3::random_in_range(&mut rng, 0.0, 165.0);
+            Box::new(Sphere::new(center, 10.0, white.clone())) as Box<dyn Hittable>
+        })
+        .collect();
+    let cluster: Box<dyn Hittable> = Box::new(LinearBvh::new(cluster, 0.0, 1.0)?);
+    world.push(Box::new(placed(
+        cluster,
+        15.0,
+        Vec3::new(-100.0, 270.0, 395.0),
+    )?));
+
+    let camera = CameraSettings::new(
+        Point3::new(478.0, 278.0, -600.0),
+        Point3::new(278.0, 278.0, 0.0),
+        40.0,
+    )
+    .with_shutter_duration(Duration::from_secs(1));
+    let mut description = describe(world, SolidSky::default(), camera).with_resolution(800, 800);
+    description.scene.lights = vec![Box::new(Shared::new(light))];
+    description.scene.samples_per_pixel = 10_000;
+    Ok(description)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_every_scene() {
+        for name in SceneDescription::builtin_names() {
+            let description =
+                SceneDescription::builtin(name).unwrap_or_else(|e| panic!("{}: {:#}", name, e));
+            assert!(!description.scene.world.is_empty(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_scene() {
+        let error = SceneDescription::builtin("teapot").unwrap_err().to_string();
+        assert!(error.starts_with("Unknown scene `teapot`, expected one of: random-spheres"));
+    }
+}
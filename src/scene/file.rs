@@ -0,0 +1,1113 @@
+//! Loader for text scene description files.
+//!
+//! A scene file describes everything needed to render an image: the camera, the sky, render
+//! settings, and the textures, materials and objects making up the world. Each non-empty line
+//! holds one statement; `#` starts a comment running to the end of the line.
+//!
+//! ```text
+//! # Two marble spheres under a blue sky.
+//! render width 384 height 216 samples 100 max_depth 50 min_depth 3 seed 0
+//! camera look_from 13 2 3 look_at 0 0 0 vfov 20 focus_dist 10
+//! sky gradient color 0.5 0.7 1.0
+//!
+//! texture marble noise scale 4 mode marble
+//! material stone lambertian albedo marble
+//! material glass dielectric ior 1.5
+//!
+//! sphere center 0 -1000 0 radius 1000 material stone
+//! sphere center 0 2 0 radius 2 material glass
+//! box min 0 0 0 max 1 1 1 material stone rotate_y 15 translate 3 0 0
+//! ```
+//!
+//! Statements start with a keyword, followed by parameters written as a name and then its
+//! values. Parameters may appear in any order, and most are optional. Vectors and colors are
+//! three numbers, ranges are two. Wherever a texture is expected, either the name of a texture
+//! defined earlier or a literal color may be given. File names may be quoted to include spaces,
+//! and are resolved relative to the scene file.
+//!
+//! # Settings
+//!
+//! | Statement | Parameters |
+//! |-----------|------------|
+//! | `render`  | `width`, `height`, `samples`, `max_depth`, `min_depth`, `seed` |
+//! | `camera`  | `projection`, `look_from`, `look_at`, `up`, `vfov` (degrees), `aperture`, `focus_dist`, `shutter` (seconds), `focal_length` (mm), `sensor` (width and height in mm), `f_stop`, `iso`, `meters_per_unit` |
+//! | `sky gradient` | `color` |
+//! | `sky solid` | `color` |
+//!
+//! The camera `projection` is one of `perspective` (the default), `orthographic`, `fisheye` or
+//! `equirectangular`; see `CameraSettings` for how each uses the other parameters. Giving any of
+//! `focal_length`, `sensor`, `f_stop`, `iso` or `meters_per_unit` makes it a `PhysicalCamera`,
+//! whose settings replace `vfov` and `aperture` and also set the exposure. The camera's aspect
+//! ratio follows from the `render` resolution. Each scene has at most one `render` and one
+//! `camera` statement. Without a `sky` statement the default `GradientSky` is used.
+//!
+//! # Textures and materials
+//!
+//! Both are declared with a name followed by their kind, e.g. `texture checks checker ...`.
+//!
+//! | Statement | Parameters |
+//! |-----------|------------|
+//! | `texture <name> checker` | `odd`, `even` (textures, both required) |
+//! | `texture <name> noise` | `scale`, `mode` (`plain`, `turbulence` or `marble`), `seed` |
+//! | `texture <name> image` | `file` (required), `filter` (`nearest` or `bilinear`), `wrap` (`repeat` or `clamp`) |
+//! | `material <name> lambertian` | `albedo` (texture) |
+//! | `material <name> metal` | `albedo` (color), `fuzz` |
+//! | `material <name> dielectric` | `ior` |
+//! | `material <name> light` | `emit` (texture) |
+//! | `material <name> isotropic` | `albedo` (texture) |
+//!
+//! # Objects
+//!
+//! | Statement | Parameters |
+//! |-----------|------------|
+//! | `sphere` | `center`, `radius` |
+//! | `moving_sphere` | `center0`, `center1`, `time` (range), `radius` |
+//! | `xy_rect` | `x`, `y` (ranges), `k` |
+//! | `xz_rect` | `x`, `z` (ranges), `k` |
+//! | `yz_rect` | `y`, `z` (ranges), `k` |
+//! | `box` | `min`, `max` |
+//! | `triangle` | `v0`, `v1`, `v2` |
+//! | `mesh` | `file` (an OBJ file, which brings its own materials) |
+//!
+//! All parameters of an object are required except for the following, which every object
+//! accepts:
+//!
+//! - `material`, naming the material to use, which is required for everything but meshes and
+//!   volumes.
+//! - `flip`, which takes no values and swaps the front and back faces.
+//! - `light`, which takes no values and marks the object as a light for the path tracer to sample
+//!   directly. Only spheres and rectangles, possibly flipped or transformed, can be sampled.
+//! - `density` and `albedo` (a texture, white by default), which fill the object with a
+//!   `ConstantMedium` of that density.
+//! - `translate` (a vector), `rotate_x`, `rotate_y`, `rotate_z` (degrees) and `scale` (a vector),
+//!   which are applied in the order they are written, after any of the above.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, format_err, Context};
+
+use super::{GradientSky, Scene, Sky, SolidSky};
+use crate::camera::{Camera, CameraSettings, Projection};
+use crate::geom::{
+    Affine, ConstantMedium, Cuboid, FlipFace, Hittable, MovingSphere, Shared, Sphere, Transform,
+    Triangle, TriangleMesh, XyRect, XzRect, YzRect,
+};
+use crate::mat::{
+    CheckeredTexture, Dielectric, DiffuseLight, FilterMode, ImageTexture, Isotropic, Lambertian,
+    Material, Metallic, NoiseMode, NoiseTexture, Texture, WrapMode,
+};
+use crate::vec3::{Color, Vec3};
+
+const DEFAULT_ASPECT_RATIO: f64 = 16.0 / 9.0;
+
+/// A scene together with the camera and image settings it was authored with.
+#[derive(Debug)]
+pub struct SceneDescription {
+    pub scene: Scene<Box<dyn Sky>>,
+    pub camera: CameraSettings,
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+}
+
+impl SceneDescription {
+    pub fn new(scene: Scene<Box<dyn Sky>>, camera: CameraSettings) -> Self {
+        SceneDescription {
+            scene,
+            camera,
+            width: None,
+            height: None,
+        }
+    }
+
+    pub fn with_resolution(mut self, width: usize, height: usize) -> Self {
+        self.width = Some(width);
+        self.height = Some(height);
+        self
+    }
+
+    /// Loads a scene file, resolving any file names it mentions relative to its directory.
+    pub fn open<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+        SceneDescription::parse(&source, base_dir)
+            .with_context(|| format!("Failed to load {}", path.display()))
+    }
+
+    /// Parses a scene file's contents, resolving any file names relative to `base_dir`.
+    pub fn parse(source: &str, base_dir: &Path) -> anyhow::Result<Self> {
+        let mut parser = Parser::new(base_dir);
+        for (index, line) in source.lines().enumerate() {
+            let tokens = tokenize(line, index + 1)?;
+            if let Some((keyword, rest)) = tokens.split_first() {
+                parser.statement(keyword, rest)?;
+            }
+        }
+
+        parser.finish()
+    }
+
+    /// Returns the aspect ratio implied by the resolution, or 16:9 if none was given.
+    pub fn aspect_ratio(&self) -> f64 {
+        match (self.width, self.height) {
+            (Some(width), Some(height)) if height > 0 => width as f64 / height as f64,
+            _ => DEFAULT_ASPECT_RATIO,
+        }
+    }
+
+    pub fn build_camera(&self) -> Box<dyn Camera> {
+        self.camera.build(self.aspect_ratio())
+    }
+}
+
+/// A word, number or quoted string, along with where it starts in the file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Token<'a> {
+    text: &'a str,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Token<'a> {
+    fn error<T: Display>(&self, message: T) -> anyhow::Error {
+        format_err!("Line {}, column {}: {}", self.line, self.column, message)
+    }
+
+    fn is_number(&self) -> bool {
+        self.text.parse::<f64>().is_ok()
+    }
+
+    fn parse<T: FromStr>(&self) -> anyhow::Result<T> {
+        self.text
+            .parse()
+            .map_err(|_| self.error(format!("Invalid number `{}`", self.text)))
+    }
+}
+
+fn tokenize(line: &str, line_no: usize) -> anyhow::Result<Vec<Token<'_>>> {
+    let mut tokens = Vec::new();
+    let mut chars = line.char_indices().enumerate().peekable();
+
+    while let Some((column, (start, c))) = chars.next() {
+        let token = |text| Token {
+            text,
+            line: line_no,
+            column: column + 1,
+        };
+
+        if c == '#' {
+            break;
+        } else if c == '"' {
+            let end = loop {
+                match chars.next() {
+                    Some((_, (end, '"'))) => break end,
+                    Some(_) => continue,
+                    None => return Err(token("").error("Unterminated string")),
+                }
+            };
+            tokens.push(token(&line[start + 1..end]));
+        } else if !c.is_whitespace() {
+            let mut end = line.len();
+            while let Some(&(_, (i, next))) = chars.peek() {
+                if next.is_whitespace() || next == '#' {
+                    end = i;
+                    break;
+                }
+                chars.next();
+            }
+            tokens.push(token(&line[start..end]));
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// The number of values a parameter takes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Arity {
+    Flag,
+    One,
+    Two,
+    Three,
+    /// Either a texture name or three color components.
+    Texture,
+}
+
+type Spec = &'static [(&'static str, Arity)];
+
+const RENDER: Spec = &[
+    ("width", Arity::One),
+    ("height", Arity::One),
+    ("samples", Arity::One),
+    ("max_depth", Arity::One),
+    ("min_depth", Arity::One),
+    ("seed", Arity::One),
+];
+
+const CAMERA: Spec = &[
+    ("projection", Arity::One),
+    ("look_from", Arity::Three),
+    ("look_at", Arity::Three),
+    ("up", Arity::Three),
+    ("vfov", Arity::One),
+    ("aperture", Arity::One),
+    ("focus_dist", Arity::One),
+    ("shutter", Arity::One),
+    ("focal_length", Arity::One),
+    ("sensor", Arity::Two),
+    ("f_stop", Arity::One),
+    ("iso", Arity::One),
+    ("meters_per_unit", Arity::One),
+];
+
+const SKY: Spec = &[("color", Arity::Three)];
+
+const OBJECT_MODIFIERS: Spec = &[
+    ("material", Arity::One),
+    ("flip", Arity::Flag),
+    ("light", Arity::Flag),
+    ("density", Arity::One),
+    ("albedo", Arity::Texture),
+    ("translate", Arity::Three),
+    ("rotate_x", Arity::One),
+    ("rotate_y", Arity::One),
+    ("rotate_z", Arity::One),
+    ("scale", Arity::Three),
+];
+
+fn texture_spec(kind: &str) -> Option<Spec> {
+    match kind {
+        "checker" => Some(&[("odd", Arity::Texture), ("even", Arity::Texture)]),
+        "noise" => Some(&[
+            ("scale", Arity::One),
+            ("mode", Arity::One),
+            ("seed", Arity::One),
+        ]),
+        "image" => Some(&[
+            ("file", Arity::One),
+            ("filter", Arity::One),
+            ("wrap", Arity::One),
+        ]),
+        _ => None,
+    }
+}
+
+fn material_spec(kind: &str) -> Option<Spec> {
+    match kind {
+        "lambertian" | "isotropic" => Some(&[("albedo", Arity::Texture)]),
+        "metal" => Some(&[("albedo", Arity::Three), ("fuzz", Arity::One)]),
+        "dielectric" => Some(&[("ior", Arity::One)]),
+        "light" => Some(&[("emit", Arity::Texture)]),
+        _ => None,
+    }
+}
+
+fn object_spec(keyword: &str) -> Option<Spec> {
+    match keyword {
+        "sphere" => Some(&[("center", Arity::Three), ("radius", Arity::One)]),
+        "moving_sphere" => Some(&[
+            ("center0", Arity::Three),
+            ("center1", Arity::Three),
+            ("time", Arity::Two),
+            ("radius", Arity::One),
+        ]),
+        "xy_rect" => Some(&[("x", Arity::Two), ("y", Arity::Two), ("k", Arity::One)]),
+        "xz_rect" => Some(&[("x", Arity::Two), ("z", Arity::Two), ("k", Arity::One)]),
+        "yz_rect" => Some(&[("y", Arity::Two), ("z", Arity::Two), ("k", Arity::One)]),
+        "box" => Some(&[("min", Arity::Three), ("max", Arity::Three)]),
+        "triangle" => Some(&[
+            ("v0", Arity::Three),
+            ("v1", Arity::Three),
+            ("v2", Arity::Three),
+        ]),
+        "mesh" => Some(&[("file", Arity::One)]),
+        _ => None,
+    }
+}
+
+#[derive(Debug)]
+struct Param<'a> {
+    key: Token<'a>,
+    values: Vec<Token<'a>>,
+}
+
+/// The parameters of a single statement, checked against the statement's specs.
+#[derive(Debug)]
+struct Params<'a> {
+    keyword: Token<'a>,
+    params: Vec<Param<'a>>,
+}
+
+impl<'a> Params<'a> {
+    fn parse(keyword: Token<'a>, tokens: &[Token<'a>], specs: &[Spec]) -> anyhow::Result<Self> {
+        let mut params: Vec<Param> = Vec::new();
+        let mut tokens = tokens.iter().copied().peekable();
+
+        while let Some(key) = tokens.next() {
+            let arity = specs
+                .iter()
+                .flat_map(|spec| spec.iter())
+                .find(|(name, _)| *name == key.text)
+                .map(|&(_, arity)| arity)
+                .ok_or_else(|| {
+                    key.error(format!(
+                        "Unknown parameter `{}` for `{}`",
+                        key.text, keyword.text
+                    ))
+                })?;
+
+            if params.iter().any(|p| p.key.text == key.text) {
+                return Err(key.error(format!("Duplicate parameter `{}`", key.text)));
+            }
+
+            let count = match arity {
+                Arity::Flag => 0,
+                Arity::One => 1,
+                Arity::Two => 2,
+                Arity::Three => 3,
+                Arity::Texture => match tokens.peek() {
+                    Some(token) if token.is_number() => 3,
+                    _ => 1,
+                },
+            };
+
+            let values: Vec<_> = tokens.by_ref().take(count).collect();
+            if values.len() < count {
+                return Err(key.error(format!(
+                    "Expected {} value(s) for `{}`, found {}",
+                    count,
+                    key.text,
+                    values.len()
+                )));
+            }
+
+            params.push(Param { key, values });
+        }
+
+        Ok(Params { keyword, params })
+    }
+
+    fn find(&self, key: &str) -> Option<&Param<'a>> {
+        self.params.iter().find(|p| p.key.text == key)
+    }
+
+    fn get<T, F>(&self, key: &str, parse: F) -> anyhow::Result<Option<T>>
+    where
+        F: FnOnce(&[Token<'a>]) -> anyhow::Result<T>,
+    {
+        self.find(key).map(|p| parse(&p.values)).transpose()
+    }
+
+    fn require<T, F>(&self, key: &str, parse: F) -> anyhow::Result<T>
+    where
+        F: FnOnce(&[Token<'a>]) -> anyhow::Result<T>,
+    {
+        self.get(key, parse)?.ok_or_else(|| self.missing(key))
+    }
+
+    fn missing(&self, key: &str) -> anyhow::Error {
+        self.keyword.error(format!(
+            "Missing parameter `{}` for `{}`",
+            key, self.keyword.text
+        ))
+    }
+
+    /// Looks up a parameter taking one of the given words, returning the matching value.
+    fn choice<T: Copy>(&self, key: &str, choices: &[(&str, T)]) -> anyhow::Result<Option<T>> {
+        self.get(key, |values| {
+            let word = &values[0];
+            choices
+                .iter()
+                .find(|(name, _)| *name == word.text)
+                .map(|&(_, value)| value)
+                .ok_or_else(|| {
+                    let names: Vec<_> = choices.iter().map(|(name, _)| *name).collect();
+                    word.error(format!(
+                        "Expected one of {} for `{}`, found `{}`",
+                        names.join(", "),
+                        key,
+                        word.text
+                    ))
+                })
+        })
+    }
+}
+
+fn number<T: FromStr>(values: &[Token]) -> anyhow::Result<T> {
+    values[0].parse()
+}
+
+fn sample_count(values: &[Token]) -> anyhow::Result<u32> {
+    match number(values)? {
+        0 => Err(values[0].error("At least one sample per pixel is needed")),
+        count => Ok(count),
+    }
+}
+
+fn seconds(values: &[Token]) -> anyhow::Result<Duration> {
+    Duration::try_from_secs_f64(number(values)?).map_err(|_| {
+        values[0].error(format!(
+            "Expected a finite, non-negative number of seconds, found `{}`",
+            values[0].text
+        ))
+    })
+}
+
+/// Parses a finite number, which `is_valid` must also accept.
+fn checked_number<F: Fn(f64) -> bool>(
+    values: &[Token],
+    is_valid: F,
+    expected: &str,
+) -> anyhow::Result<f64> {
+    let value: f64 = number(values)?;
+    if value.is_finite() && is_valid(value) {
+        Ok(value)
+    } else {
+        Err(values[0].error(format!("Expected {}, found `{}`", expected, values[0].text)))
+    }
+}
+
+fn positive(values: &[Token]) -> anyhow::Result<f64> {
+    checked_number(values, |value| value > 0.0, "a positive number")
+}
+
+fn non_negative(values: &[Token]) -> anyhow::Result<f64> {
+    checked_number(values, |value| value >= 0.0, "a non-negative number")
+}
+
+fn field_of_view(values: &[Token]) -> anyhow::Result<f64> {
+    checked_number(
+        values,
+        |degrees| degrees > 0.0 && degrees <= 360.0,
+        "an angle above 0 and up to 360 degrees",
+    )
+}
+
+fn range(values: &[Token]) -> anyhow::Result<(f64, f64)> {
+    Ok((values[0].parse()?, values[1].parse()?))
+}
+
+fn vec3(values: &[Token]) -> anyhow::Result<Vec3> {
+    Ok(Vec3::new(
+        values[0].parse()?,
+        values[1].parse()?,
+        values[2].parse()?,
+    ))
+}
+
+fn projection(values: &[Token]) -> anyhow::Result<Projection> {
+    let word = &values[0];
+    word.text.parse().map_err(|e| word.error(e))
+}
+
+fn word<'a>(values: &[Token<'a>]) -> anyhow::Result<Token<'a>> {
+    Ok(values[0])
+}
+
+/// Accumulates the state built up by the statements seen so far.
+struct Parser<'a> {
+    base_dir: &'a Path,
+    textures: HashMap<String, Arc<dyn Texture>>,
+    materials: HashMap<String, Arc<dyn Material>>,
+    world: Vec<Box<dyn Hittable>>,
+    lights: Vec<Box<dyn Hittable>>,
+    sky: Option<Box<dyn Sky>>,
+    camera: CameraSettings,
+    has_camera: bool,
+    render: Option<Params<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(base_dir: &'a Path) -> Self {
+        Parser {
+            base_dir,
+            textures: HashMap::new(),
+            materials: HashMap::new(),
+            world: Vec::new(),
+            lights: Vec::new(),
+            sky: None,
+            camera: CameraSettings::default(),
+            has_camera: false,
+            render: None,
+        }
+    }
+
+    fn statement(&mut self, keyword: &Token<'a>, rest: &[Token<'a>]) -> anyhow::Result<()> {
+        let keyword = *keyword;
+        match keyword.text {
+            "render" => {
+                if self.render.is_some() {
+                    return Err(keyword.error("Duplicate `render` statement"));
+                }
+                self.render = Some(Params::parse(keyword, rest, &[RENDER])?);
+            }
+            "camera" => {
+                if self.has_camera {
+                    return Err(keyword.error("Duplicate `camera` statement"));
+                }
+                self.has_camera = true;
+                self.camera(&Params::parse(keyword, rest, &[CAMERA])?)?;
+            }
+            "sky" => {
+                let (kind, rest) = split_word(keyword, rest, "sky kind")?;
+                let params = Params::parse(kind, rest, &[SKY])?;
+                let color = params.get("color", vec3)?;
+                let sky: Box<dyn Sky> = match kind.text {
+                    "gradient" => {
+                        Box::new(color.map(|color| GradientSky { color }).unwrap_or_default())
+                    }
+                    "solid" => Box::new(color.map(|color| SolidSky { color }).unwrap_or_default()),
+                    _ => return Err(kind.error(format!("Unknown sky `{}`", kind.text))),
+                };
+                self.sky = Some(sky);
+            }
+            "texture" => {
+                let (name, rest) = split_word(keyword, rest, "texture name")?;
+                let (kind, rest) = split_word(name, rest, "texture kind")?;
+                let spec = texture_spec(kind.text)
+                    .ok_or_else(|| kind.error(format!("Unknown texture `{}`", kind.text)))?;
+                let texture = self.texture(&Params::parse(kind, rest, &[spec])?)?;
+                if self
+                    .textures
+                    .insert(name.text.to_owned(), texture)
+                    .is_some()
+                {
+                    return Err(name.error(format!("Texture `{}` is already defined", name.text)));
+                }
+            }
+            "material" => {
+                let (name, rest) = split_word(keyword, rest, "material name")?;
+                let (kind, rest) = split_word(name, rest, "material kind")?;
+                let spec = material_spec(kind.text)
+                    .ok_or_else(|| kind.error(format!("Unknown material `{}`", kind.text)))?;
+                let material = self.material(&Params::parse(kind, rest, &[spec])?)?;
+                if self
+                    .materials
+                    .insert(name.text.to_owned(), material)
+                    .is_some()
+                {
+                    return Err(name.error(format!("Material `{}` is already defined", name.text)));
+                }
+            }
+            other => {
+                let spec = object_spec(other)
+                    .ok_or_else(|| keyword.error(format!("Unknown statement `{}`", other)))?;
+                let params = Params::parse(keyword, rest, &[spec, OBJECT_MODIFIERS])?;
+                let object = self.object(&params)?;
+                if let Some(param) = params.find("light") {
+                    let can_sample = matches!(other, "sphere" | "xy_rect" | "xz_rect" | "yz_rect");
+                    if !can_sample || params.find("density").is_some() {
+                        return Err(param
+                            .key
+                            .error("Only spheres and rectangles can be marked as `light`"));
+                    }
+                    let light = Arc::from(object);
+                    self.lights.push(Box::new(Shared::new(Arc::clone(&light))));
+                    self.world.push(Box::new(Shared::new(light)));
+                } else {
+                    self.world.push(object);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn camera(&mut self, params: &Params<'a>) -> anyhow::Result<()> {
+        let camera = &mut self.camera;
+        if let Some(projection) = params.get("projection", projection)? {
+            camera.projection = projection;
+        }
+        if let Some(look_from) = params.get("look_from", vec3)? {
+            camera.look_from = look_from;
+        }
+        if let Some(look_at) = params.get("look_at", vec3)? {
+            camera.look_at = look_at;
+        }
+        if let Some(up) = params.get("up", vec3)? {
+            camera.up_vec = up;
+        }
+        if let Some(vfov) = params.get("vfov", field_of_view)? {
+            camera.vertical_fov_deg = vfov;
+        }
+        if let Some(aperture) = params.get("aperture", non_negative)? {
+            camera.aperture = aperture;
+        }
+        if let Some(focus_dist) = params.get("focus_dist", positive)? {
+            camera.focus_dist = Some(focus_dist);
+        }
+
+        let physical_keys = ["focal_length", "sensor", "f_stop", "iso", "meters_per_unit"];
+        if physical_keys.iter().any(|key| params.find(key).is_some()) {
+            let physical = camera.physical.get_or_insert_with(Default::default);
+            if let Some(focal_length) = params.get("focal_length", number)? {
+                physical.focal_length_mm = focal_length;
+            }
+            if let Some(sensor_size) = params.get("sensor", range)? {
+                physical.sensor_size_mm = sensor_size;
+            }
+            if let Some(f_stop) = params.get("f_stop", number)? {
+                physical.f_stop = f_stop;
+            }
+            if let Some(iso) = params.get("iso", number)? {
+                physical.iso = iso;
+            }
+            if let Some(meters_per_unit) = params.get("meters_per_unit", number)? {
+                physical.meters_per_unit = meters_per_unit;
+            }
+        }
+
+        if let Some(shutter) = params.get("shutter", seconds)? {
+            match &mut camera.physical {
+                Some(physical) => physical.shutter = shutter,
+                None => camera.shutter_duration = shutter,
+            }
+        }
+        if let Some(physical) = &camera.physical {
+            physical.validate().map_err(|e| params.keyword.error(e))?;
+        }
+        Ok(())
+    }
+
+    /// Resolves a texture parameter, which is either a texture name or a literal color.
+    fn texture_value(&self, values: &[Token]) -> anyhow::Result<Arc<dyn Texture>> {
+        if values.len() == 3 {
+            return Ok(Arc::new(vec3(values)?));
+        }
+
+        let name = &values[0];
+        self.textures
+            .get(name.text)
+            .cloned()
+            .ok_or_else(|| name.error(format!("Unknown texture `{}`", name.text)))
+    }
+
+    fn texture(&self, params: &Params<'a>) -> anyhow::Result<Arc<dyn Texture>> {
+        Ok(match params.keyword.text {
+            "checker" => Arc::new(CheckeredTexture::new(
+                params.require("odd", |v| self.texture_value(v))?,
+                params.require("even", |v| self.texture_value(v))?,
+            )),
+            "noise" => {
+                let mut noise =
+                    NoiseTexture::with_scale(params.get("scale", number)?.unwrap_or(1.0));
+                if let Some(seed) = params.get("seed", number)? {
+                    noise = noise.with_seed(seed);
+                }
+                let modes = [
+                    ("plain", NoiseMode::Plain),
+                    ("turbulence", NoiseMode::Turbulence),
+                    ("marble", NoiseMode::Marble),
+                ];
+                Arc::new(noise.with_mode(params.choice("mode", &modes)?.unwrap_or_default()))
+            }
+            "image" => {
+                let file = params.require("file", word)?;
+                let filters = [
+                    ("nearest", FilterMode::Nearest),
+                    ("bilinear", FilterMode::Bilinear),
+                ];
+                let wraps = [("repeat", WrapMode::Repeat), ("clamp", WrapMode::Clamp)];
+
+                let image = ImageTexture::open(self.base_dir.join(file.text))
+                    .map_err(|e| file.error(format!("{:#}", e)))?;
+                Arc::new(
+                    image
+                        .with_filter(params.choice("filter", &filters)?.unwrap_or_default())
+                        .with_wrap(params.choice("wrap", &wraps)?.unwrap_or_default()),
+                )
+            }
+            _ => unreachable!("texture kinds are checked by `texture_spec`"),
+        })
+    }
+
+    fn material(&self, params: &Params<'a>) -> anyhow::Result<Arc<dyn Material>> {
+        let texture = |key, default: Color| -> anyhow::Result<Arc<dyn Texture>> {
+            Ok(params
+                .get(key, |v| self.texture_value(v))?
+                .unwrap_or_else(|| Arc::new(default)))
+        };
+
+        Ok(match params.keyword.text {
+            "lambertian" => Arc::new(Lambertian::new(texture(
+                "albedo",
+                Color::new(0.5, 0.5, 0.5),
+            )?)),
+            "isotropic" => Arc::new(Isotropic::new(texture("albedo", Color::ones())?)),
+            "light" => Arc::new(DiffuseLight::new(texture("emit", Color::ones())?)),
+            "metal" => {
+                let default = Metallic::default();
+                Arc::new(Metallic::new(
+                    params.get("albedo", vec3)?.unwrap_or(default.albedo),
+                    params.get("fuzz", number)?.unwrap_or(default.fuzz),
+                ))
+            }
+            "dielectric" => Arc::new(Dielectric::new(params.get("ior", number)?.unwrap_or(1.5))),
+            _ => unreachable!("material kinds are checked by `material_spec`"),
+        })
+    }
+
+    fn object(&self, params: &Params<'a>) -> anyhow::Result<Box<dyn Hittable>> {
+        let is_volume = params.find("density").is_some();
+        let material = match params.find("material") {
+            Some(param) => {
+                let name = &param.values[0];
+                self.materials
+                    .get(name.text)
+                    .cloned()
+                    .ok_or_else(|| name.error(format!("Unknown material `{}`", name.text)))?
+            }
+            // Volumes ignore their boundary's material, and meshes bring their own.
+            None if is_volume || params.keyword.text == "mesh" => {
+                Arc::new(Lambertian::default()) as Arc<dyn Material>
+            }
+            None => return Err(params.missing("material")),
+        };
+
+        let mut object: Box<dyn Hittable> = match params.keyword.text {
+            "sphere" => Box::new(Sphere::new(
+                params.require("center", vec3)?,
+                params.require("radius", positive)?,
+                material,
+            )),
+            "moving_sphere" => Box::new(MovingSphere::new(
+                (
+                    params.require("center0", vec3)?,
+                    params.require("center1", vec3)?,
+                ),
+                params.require("time", range)?,
+                params.require("radius", positive)?,
+                material,
+            )),
+            "xy_rect" => Box::new(XyRect::new(
+                params.require("x", range)?,
+                params.require("y", range)?,
+                params.require("k", number)?,
+                material,
+            )),
+            "xz_rect" => Box::new(XzRect::new(
+                params.require("x", range)?,
+                params.require("z", range)?,
+                params.require("k", number)?,
+                material,
+            )),
+            "yz_rect" => Box::new(YzRect::new(
+                params.require("y", range)?,
+                params.require("z", range)?,
+                params.require("k", number)?,
+                material,
+            )),
+            "box" => Box::new(Cuboid::new(
+                params.require("min", vec3)?,
+                params.require("max", vec3)?,
+                material,
+            )),
+            "triangle" => Box::new(Triangle::new(
+                [
+                    params.require("v0", vec3)?,
+                    params.require("v1", vec3)?,
+                    params.require("v2", vec3)?,
+                ],
+                material,
+            )),
+            "mesh" => {
+                let file = params.require("file", word)?;
+                let mesh = TriangleMesh::from_obj(self.base_dir.join(file.text))
+                    .map_err(|e| file.error(format!("{:#}", e)))?;
+                Box::new(mesh)
+            }
+            _ => unreachable!("object kinds are checked by `object_spec`"),
+        };
+
+        if params.find("flip").is_some() {
+            // A single-element list stands in for the boxed object, which `FlipFace` can't hold.
+            object = Box::new(FlipFace::new(vec![object]));
+        }
+
+        if let Some(density) = params.get("density", positive)? {
+            let albedo = params
+                .get("albedo", |v| self.texture_value(v))?
+                .unwrap_or_else(|| Arc::new(Color::ones()));
+            object = Box::new(ConstantMedium::new(object, density, albedo));
+        } else if let Some(param) = params.find("albedo") {
+            return Err(param
+                .key
+                .error("`albedo` is only allowed together with `density`"));
+        }
+
+        let mut affine = None;
+        for param in &params.params {
+            let step = match param.key.text {
+                "translate" => Affine::translation(vec3(&param.values)?),
+                "rotate_x" => Affine::rotation(Vec3::new(1.0, 0.0, 0.0), number(&param.values)?),
+                "rotate_y" => Affine::rotation(Vec3::new(0.0, 1.0, 0.0), number(&param.values)?),
+                "rotate_z" => Affine::rotation(Vec3::new(0.0, 0.0, 1.0), number(&param.values)?),
+                "scale" => Affine::scaling(vec3(&param.values)?),
+                _ => continue,
+            };
+            affine = Some(affine.map_or(step, |affine: Affine| affine.then(step)));
+        }
+
+        if let Some(affine) = affine {
+            let transform = Transform::new(object, affine).map_err(|e| params.keyword.error(e))?;
+            object = Box::new(transform);
+        }
+
+        Ok(object)
+    }
+
+    fn finish(self) -> anyhow::Result<SceneDescription> {
+        if self.world.is_empty() {
+            return Err(anyhow!("Scene contains no objects"));
+        }
+
+        let sky = self.sky.unwrap_or_else(|| Box::new(GradientSky::default()));
+        let scene = Scene::new(self.world, sky).with_lights(self.lights);
+        let mut description = SceneDescription::new(scene, self.camera);
+
+        if let Some(render) = &self.render {
+            description.width = render.get("width", number)?;
+            description.height = render.get("height", number)?;
+            if let Some(samples) = render.get("samples", sample_count)? {
+                description.scene.samples_per_pixel = samples;
+            }
+            if let Some(depth) = render.get("max_depth", number)? {
+                description.scene.max_bounce_depth = depth;
+            }
+            if let Some(depth) = render.get("min_depth", number)? {
+                description.scene.min_bounce_depth = depth;
+            }
+            if let Some(seed) = render.get("seed", number)? {
+                description.scene.seed = seed;
+            }
+        }
+
+        Ok(description)
+    }
+}
+
+/// Splits off the word following `previous`, such as the name in `texture <name> ...`.
+fn split_word<'a, 'b>(
+    previous: Token<'a>,
+    rest: &'b [Token<'a>],
+    what: &str,
+) -> anyhow::Result<(Token<'a>, &'b [Token<'a>])> {
+    rest.split_first()
+        .map(|(word, rest)| (*word, rest))
+        .ok_or_else(|| previous.error(format!("Expected {} after `{}`", what, previous.text)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray::Ray;
+    use crate::vec3::Point3;
+
+    fn parse(source: &str) -> anyhow::Result<SceneDescription> {
+        SceneDescription::parse(source, Path::new(""))
+    }
+
+    fn error(source: &str) -> String {
+        parse(source).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn parses_complete_scene() {
+        let source = r#"
+            # A comment on its own line.
+            render width 200 height 100 samples 8 max_depth 5 min_depth 2 seed 3
+            camera look_from 0 0 5 look_at 0 0 0 vfov 40 aperture 0.1 shutter 1
+            sky solid color 0.1 0.2 0.3
+
+            texture checks checker odd 0 0 0 even 1 1 1
+            texture marble noise scale 4 mode marble seed 2
+            material ground lambertian albedo checks # trailing comment
+            material stone lambertian albedo marble
+            material mirror metal albedo 0.8 0.8 0.8 fuzz 0.1
+            material glass dielectric ior 1.5
+            material lamp light emit 4 4 4
+
+            sphere center 0 -100 0 radius 100 material ground
+            sphere center 0 0 0 radius 1 material glass
+            moving_sphere center0 0 0 0 center1 0 1 0 time 0 1 radius 0.5 material stone
+            xz_rect x -1 1 z -1 1 k 3 material lamp flip light
+            box min 0 0 0 max 1 1 1 material mirror rotate_y 15 translate 2 0 0
+            triangle v0 0 0 0 v1 1 0 0 v2 0 1 0 material stone
+            sphere center 0 0 0 radius 10 density 0.01 albedo 1 1 1
+        "#;
+
+        let description = parse(source).unwrap();
+        let scene = &description.scene;
+        assert_eq!(scene.world.len(), 7);
+        assert_eq!(scene.lights.len(), 1);
+        assert_eq!(scene.samples_per_pixel, 8);
+        assert_eq!(scene.max_bounce_depth, 5);
+        assert_eq!(scene.min_bounce_depth, 2);
+        assert_eq!(scene.seed, 3);
+        assert_eq!(
+            (description.width, description.height),
+            (Some(200), Some(100))
+        );
+        assert_eq!(description.aspect_ratio(), 2.0);
+
+        let ray = Ray::new(Point3::zeros(), Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(scene.sky.color(&ray), Color::new(0.1, 0.2, 0.3));
+        assert_eq!(description.camera.look_from, Point3::new(0.0, 0.0, 5.0));
+        assert_eq!(description.camera.vertical_fov_deg, 40.0);
+        assert_eq!(description.camera.shutter_duration, Duration::from_secs(1));
+        assert_eq!(description.camera.physical, None);
+    }
+
+    #[test]
+    fn parses_physical_camera() {
+        let description = parse(
+            "camera focal_length 35 f_stop 4 shutter 0.5 sensor 24 16\n\
+             material white lambertian\n\
+             sphere center 0 0 0 radius 1 material white",
+        )
+        .unwrap();
+        let physical = description.camera.physical.unwrap();
+        assert_eq!(physical.focal_length_mm, 35.0);
+        assert_eq!(physical.sensor_size_mm, (24.0, 16.0));
+        assert_eq!(physical.f_stop, 4.0);
+        assert_eq!(physical.iso, 100.0);
+        assert_eq!(physical.shutter, Duration::from_millis(500));
+        assert_eq!(description.camera.shutter_duration, Duration::from_secs(0));
+    }
+
+    #[test]
+    fn loads_example_scene() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes/two_perlin_spheres.scene");
+        let description = SceneDescription::open(path).unwrap();
+        assert_eq!(description.scene.world.len(), 2);
+        assert_eq!(
+            (description.width, description.height),
+            (Some(384), Some(216))
+        );
+    }
+
+    #[test]
+    fn transforms_apply_in_order() {
+        let source = "material m lambertian\nbox min -1 -1 -1 max 1 1 1 material m translate 2 0 0 rotate_z 90";
+        let scene = parse(source).unwrap().scene;
+        let bounds = scene.world[0].bounding_box(0.0, 0.0).unwrap();
+        assert!((bounds.min.y - 1.0).abs() < 1e-3 && (bounds.max.y - 3.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn reports_line_and_column() {
+        let source = "material m lambertian\n\nsphere center 0 0 zero radius 1 material m";
+        assert_eq!(error(source), "Line 3, column 19: Invalid number `zero`");
+
+        assert_eq!(
+            error("material m lambertian\nsphere center 0 0 0 material m"),
+            "Line 2, column 1: Missing parameter `radius` for `sphere`"
+        );
+        assert_eq!(
+            error("sphere center 0 0 0 radius 1 colour red"),
+            "Line 1, column 30: Unknown parameter `colour` for `sphere`"
+        );
+        assert_eq!(
+            error("  cone radius 1"),
+            "Line 1, column 3: Unknown statement `cone`"
+        );
+        assert_eq!(
+            error("camera look_at 1 2"),
+            "Line 1, column 8: Expected 3 value(s) for `look_at`, found 2"
+        );
+        assert_eq!(
+            error("texture t noise mode wavy"),
+            "Line 1, column 22: Expected one of plain, turbulence, marble for `mode`, found `wavy`"
+        );
+        assert_eq!(
+            error("camera projection pinhole"),
+            "Line 1, column 19: Unknown projection `pinhole`, expected one of: perspective, \
+             orthographic, fisheye, equirectangular"
+        );
+        assert_eq!(
+            error("material m light\nbox min 0 0 0 max 1 1 1 material m light"),
+            "Line 2, column 36: Only spheres and rectangles can be marked as `light`"
+        );
+        assert_eq!(
+            error("sphere center 0 0 0 radius 1 density 1 light"),
+            "Line 1, column 40: Only spheres and rectangles can be marked as `light`"
+        );
+        assert_eq!(
+            error("camera f_stop 0"),
+            "Line 1, column 1: The physical camera's f-stop must be positive, found 0"
+        );
+        assert_eq!(
+            error("camera focal_length 50 shutter 0"),
+            "Line 1, column 1: The physical camera's shutter time must be positive, found 0"
+        );
+        assert_eq!(
+            error("box min 0 0 0 max 1 1 1 material m\nmaterial m lambertian"),
+            "Line 1, column 34: Unknown material `m`"
+        );
+        assert!(error("texture t image file \"no such file.png\"")
+            .starts_with("Line 1, column 22: Failed to read no such file.png"));
+        assert_eq!(
+            error("sky solid color \"1"),
+            "Line 1, column 17: Unterminated string"
+        );
+        assert_eq!(
+            error("render samples 0\nsphere center 0 0 0 radius 1 density 1"),
+            "Line 1, column 16: At least one sample per pixel is needed"
+        );
+        assert_eq!(
+            error("camera shutter -1"),
+            "Line 1, column 16: Expected a finite, non-negative number of seconds, found `-1`"
+        );
+        assert_eq!(
+            error("material m lambertian\nsphere center 0 0 0 radius 0 material m"),
+            "Line 2, column 28: Expected a positive number, found `0`"
+        );
+        assert_eq!(
+            error("moving_sphere center0 0 0 0 center1 0 1 0 time 0 1 radius -1 density 1"),
+            "Line 1, column 59: Expected a positive number, found `-1`"
+        );
+        assert_eq!(
+            error("sphere center 0 0 0 radius 1 density NaN"),
+            "Line 1, column 38: Expected a positive number, found `NaN`"
+        );
+        assert_eq!(
+            error("camera vfov 0"),
+            "Line 1, column 13: Expected an angle above 0 and up to 360 degrees, found `0`"
+        );
+        assert_eq!(
+            error("camera aperture -0.1"),
+            "Line 1, column 17: Expected a non-negative number, found `-0.1`"
+        );
+        assert_eq!(
+            error("camera focus_dist inf"),
+            "Line 1, column 19: Expected a positive number, found `inf`"
+        );
+    }
+
+    #[test]
+    fn rejects_duplicates_and_empty_scenes() {
+        assert_eq!(
+            error("material m lambertian\nmaterial m metal"),
+            "Line 2, column 10: Material `m` is already defined"
+        );
+        assert_eq!(
+            error("camera vfov 20 vfov 30"),
+            "Line 1, column 16: Duplicate parameter `vfov`"
+        );
+        assert_eq!(
+            error("camera vfov 20\ncamera aperture 0.1"),
+            "Line 2, column 1: Duplicate `camera` statement"
+        );
+        assert_eq!(
+            error("# nothing here\ncamera vfov 20"),
+            "Scene contains no objects"
+        );
+    }
+}
@@ -0,0 +1,197 @@
+//! Post-processing that maps rendered radiance onto displayable colors.
+
+use std::str::FromStr;
+
+use anyhow::format_err;
+
+use crate::vec3::Color;
+
+/// Curve used to compress unbounded radiance into `0.0..=1.0`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Operator {
+    /// Clips every channel to `1.0`, leaving darker values untouched.
+    #[default]
+    Clamp,
+    /// `L / (1 + L)` applied to luminance, which never quite reaches white.
+    Reinhard,
+    /// Reinhard with a white point, above which luminance saturates to white.
+    ExtendedReinhard,
+    /// Krzysztof Narkowicz's fit of the ACES filmic curve, applied per channel.
+    Aces,
+}
+
+impl FromStr for Operator {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "clamp" => Ok(Operator::Clamp),
+            "reinhard" => Ok(Operator::Reinhard),
+            "extended-reinhard" => Ok(Operator::ExtendedReinhard),
+            "aces" => Ok(Operator::Aces),
+            _ => Err(format_err!(
+                "Unknown tone mapping operator `{}`, expected one of: clamp, reinhard, \
+                 extended-reinhard, aces",
+                s
+            )),
+        }
+    }
+}
+
+/// Scales linear radiance by an exposure and then applies a tone mapping `Operator`.
+///
+/// The result is still linear; `output::linear_to_srgb()` encodes it for display.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ToneMapper {
+    /// Exposure compensation in stops, where each stop doubles the brightness.
+    pub exposure: f64,
+    pub operator: Operator,
+    /// Luminance mapped to white by `Operator::ExtendedReinhard`, after exposure.
+    pub white_point: f64,
+}
+
+impl ToneMapper {
+    pub fn new(operator: Operator) -> Self {
+        ToneMapper {
+            operator,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_exposure(mut self, val: f64) -> Self {
+        self.exposure = val;
+        self
+    }
+
+    pub fn with_white_point(mut self, val: f64) -> Self {
+        self.white_point = val;
+        self
+    }
+
+    /// Returns the factor radiance is multiplied by before tone mapping.
+    pub fn exposure_scale(&self) -> f64 {
+        2f64.powf(self.exposure)
+    }
+
+    pub fn apply(&self, color: Color) -> Color {
+        let color = color * self.exposure_scale();
+        match self.operator {
+            Operator::Clamp => clamp(color),
+            Operator::Reinhard => scale_luminance(color, |l| l / (1.0 + l)),
+            Operator::ExtendedReinhard => {
+                let white_squared = self.white_point.powi(2);
+                scale_luminance(color, |l| l * (1.0 + l / white_squared) / (1.0 + l))
+            }
+            Operator::Aces => {
+                let aces = |x: f64| {
+                    let x = x.max(0.0);
+                    (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)
+                };
+                clamp(Color::new(aces(color.x), aces(color.y), aces(color.z)))
+            }
+        }
+    }
+
+    pub fn apply_all(&self, pixels: &mut [Color]) {
+        for pixel in pixels {
+            *pixel = self.apply(*pixel);
+        }
+    }
+}
+
+impl Default for ToneMapper {
+    fn default() -> Self {
+        ToneMapper {
+            exposure: 0.0,
+            operator: Operator::default(),
+            white_point: 1.0,
+        }
+    }
+}
+
+/// Returns the relative luminance of a linear Rec. 709 color.
+#[inline]
+pub fn luminance(color: Color) -> f64 {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+}
+
+fn clamp(color: Color) -> Color {
+    Color::new(
+        color.x.clamp(0.0, 1.0),
+        color.y.clamp(0.0, 1.0),
+        color.z.clamp(0.0, 1.0),
+    )
+}
+
+/// Rescales `color` so that its luminance becomes `curve(luminance)`, preserving its hue.
+fn scale_luminance<F: Fn(f64) -> f64>(color: Color, curve: F) -> Color {
+    let l = luminance(color);
+    if l <= 0.0 {
+        return Color::zeros();
+    }
+    clamp(color * (curve(l) / l))
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use super::*;
+
+    #[test]
+    fn exposure_doubles_per_stop() {
+        let mapper = ToneMapper::new(Operator::Clamp).with_exposure(1.0);
+        assert_eq!(
+            mapper.apply(Color::new(0.1, 0.2, 0.4)),
+            Color::new(0.2, 0.4, 0.8)
+        );
+        assert_eq!(mapper.apply(Color::new(1.0, 0.0, 0.0)).x, 1.0);
+    }
+
+    #[test]
+    fn operators_stay_in_range_and_preserve_black() {
+        let operators = [
+            Operator::Clamp,
+            Operator::Reinhard,
+            Operator::ExtendedReinhard,
+            Operator::Aces,
+        ];
+        for &operator in &operators {
+            let mapper = ToneMapper::new(operator).with_white_point(4.0);
+            assert_eq!(mapper.apply(Color::zeros()), Color::zeros());
+            for &value in &[0.01, 0.5, 1.0, 10.0, 1000.0] {
+                let mapped = mapper.apply(Color::new(value, value * 0.5, value * 0.1));
+                for &channel in &[mapped.x, mapped.y, mapped.z] {
+                    assert!(
+                        (0.0..=1.0).contains(&channel),
+                        "{:?}: {}",
+                        operator,
+                        channel
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn reinhard_curves() {
+        let grey = |v: f64| Color::new(v, v, v);
+        let reinhard = ToneMapper::new(Operator::Reinhard);
+        assert_float_eq!(reinhard.apply(grey(1.0)).x, 0.5, abs <= 1e-12);
+
+        // The extended curve reaches exactly white at the white point.
+        let extended = ToneMapper::new(Operator::ExtendedReinhard).with_white_point(4.0);
+        assert_float_eq!(extended.apply(grey(4.0)).x, 1.0, abs <= 1e-12);
+        assert!(extended.apply(grey(1.0)).x > reinhard.apply(grey(1.0)).x);
+    }
+
+    #[test]
+    fn parses_operator_names() {
+        assert_eq!("aces".parse::<Operator>().unwrap(), Operator::Aces);
+        assert_eq!(
+            "extended-reinhard".parse::<Operator>().unwrap(),
+            Operator::ExtendedReinhard
+        );
+        assert!("filmic".parse::<Operator>().is_err());
+    }
+}
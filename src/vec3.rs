@@ -7,7 +7,7 @@ use rand::Rng;
 pub type Color = Vec3;
 pub type Point3 = Vec3;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct Vec3 {
     pub x: f64,
     pub y: f64,
@@ -30,13 +30,11 @@ impl Vec3 {
         Vec3::new(0.0, 0.0, 0.0)
     }
 
-    pub fn random() -> Self {
-        let mut rng = rand::thread_rng();
+    pub fn random<R: Rng + ?Sized>(rng: &mut R) -> Self {
         Vec3::new(rng.gen(), rng.gen(), rng.gen())
     }
 
-    pub fn random_in_range(min: f64, max: f64) -> Self {
-        let mut rng = rand::thread_rng();
+    pub fn random_in_range<R: Rng + ?Sized>(rng: &mut R, min: f64, max: f64) -> Self {
         Vec3::new(
             rng.gen_range(min, max),
             rng.gen_range(min, max),
@@ -44,16 +42,15 @@ impl Vec3 {
         )
     }
 
-    pub fn random_unit() -> Self {
-        let mut rng = rand::thread_rng();
+    pub fn random_unit<R: Rng + ?Sized>(rng: &mut R) -> Self {
         let a: f64 = rng.gen_range(0.0, 2.0 * std::f64::consts::PI);
         let z: f64 = rng.gen_range(-1.0, 1.0);
         let r = (1.0 - z.powi(2)).sqrt();
         Vec3::new(r * a.cos(), r * a.sin(), z)
     }
 
-    pub fn random_in_hemisphere(normal: Self) -> Self {
-        let in_unit_sphere = Vec3::random_in_unit_sphere();
+    pub fn random_in_hemisphere<R: Rng + ?Sized>(rng: &mut R, normal: Self) -> Self {
+        let in_unit_sphere = Vec3::random_in_unit_sphere(rng);
         if in_unit_sphere.dot(normal) > 0.0 {
             in_unit_sphere
         } else {
@@ -61,8 +58,7 @@ impl Vec3 {
         }
     }
 
-    pub fn random_in_unit_disk() -> Self {
-        let mut rng = rand::thread_rng();
+    pub fn random_in_unit_disk<R: Rng + ?Sized>(rng: &mut R) -> Self {
         loop {
             let p = Vec3::new(rng.gen_range(-1.0, 1.0), rng.gen_range(-1.0, 1.0), 0.0);
             if p.len_squared() < 1.0 {
@@ -71,9 +67,9 @@ impl Vec3 {
         }
     }
 
-    pub fn random_in_unit_sphere() -> Self {
+    pub fn random_in_unit_sphere<R: Rng + ?Sized>(rng: &mut R) -> Self {
         loop {
-            let p = Vec3::random_in_range(-1.0, 1.0);
+            let p = Vec3::random_in_range(rng, -1.0, 1.0);
             if p.len_squared() < 1.0 {
                 return p;
             }
@@ -105,11 +101,29 @@ impl Vec3 {
         }
     }
 
+    #[inline]
+    pub fn is_finite(self) -> bool {
+        self.x.is_finite() && self.y.is_finite() && self.z.is_finite()
+    }
+
     #[inline]
     pub fn to_unit(self) -> Self {
         self / self.len()
     }
 
+    /// Returns two unit vectors `(u, v)` such that `u`, `v` and this unit vector form a
+    /// right-handed orthonormal basis.
+    pub fn orthonormal_basis(self) -> (Self, Self) {
+        let helper = if self.x.abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let v = self.cross(helper).to_unit();
+        let u = v.cross(self);
+        (u, v)
+    }
+
     #[inline]
     pub fn reflect(self, surface_normal: Self) -> Self {
         self - 2.0 * self.dot(surface_normal) * surface_normal
@@ -262,6 +276,20 @@ impl Sum for Vec3 {
 mod tests {
     use super::*;
 
+    #[test]
+    fn orthonormal_basis_is_right_handed() {
+        for &w in &[
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(1.0, -2.0, 3.0).to_unit(),
+        ] {
+            let (u, v) = w.orthonormal_basis();
+            assert!((u.len() - 1.0).abs() < 1e-12 && (v.len() - 1.0).abs() < 1e-12);
+            assert!(u.dot(v).abs() < 1e-12 && u.dot(w).abs() < 1e-12 && v.dot(w).abs() < 1e-12);
+            assert!((u.cross(v) - w).len() < 1e-12);
+        }
+    }
+
     #[test]
     fn cross_product_simple() {
         let lhs = Vec3::new(1.0, 0.0, 0.0);